@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::Mac;
+
+#[cfg(test)]
+mod test {
+    use super::{load_config_from, parse_config, parse_hosts, Config, HostEntry};
+    #[cfg(feature = "yaml")]
+    use super::{load_hosts, parse_hosts_yaml};
+    use crate::Mac;
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn can_parse_hosts_file() {
+        let hosts = parse_hosts("[officepc]\n\
+                                 mac = \"00:1a:2b:3c:4d:5e\"\n\
+                                 broadcast = \"192.168.1.255\"\n\
+                                 \n\
+                                 [nas]\n\
+                                 mac = \"00-11-22-33-44-55\"\n\
+                                 broadcast = \"nas.lan\"\n")
+            .unwrap();
+
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts["officepc"],
+                   HostEntry {
+                       mac: Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e)),
+                       broadcast: "192.168.1.255".to_string(),
+                       port: None,
+                   });
+        assert_eq!(hosts["nas"],
+                   HostEntry {
+                       mac: Mac::new((0x00, 0x11, 0x22, 0x33, 0x44, 0x55)),
+                       broadcast: "nas.lan".to_string(),
+                       port: None,
+                   });
+    }
+
+    #[test]
+    fn can_parse_host_ports() {
+        let hosts = parse_hosts("[officepc]\n\
+                                 mac = \"00:1a:2b:3c:4d:5e\"\n\
+                                 broadcast = \"192.168.1.255\"\n\
+                                 port = 7\n\
+                                 \n\
+                                 [nas]\n\
+                                 mac = \"00-11-22-33-44-55\"\n\
+                                 broadcast = \"nas.lan\"\n")
+            .unwrap();
+
+        assert_eq!(hosts["officepc"].port, Some(7));
+        assert_eq!(hosts["nas"].port, None);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn can_parse_yaml_hosts_file() {
+        let toml = parse_hosts("[officepc]\n\
+                                mac = \"00:1a:2b:3c:4d:5e\"\n\
+                                broadcast = \"192.168.1.255\"\n\
+                                port = 7\n\
+                                \n\
+                                [nas]\n\
+                                mac = \"00-11-22-33-44-55\"\n\
+                                broadcast = \"nas.lan\"\n")
+            .unwrap();
+        let yaml = parse_hosts_yaml("officepc:\n\
+                                     \x20 mac: \"00:1a:2b:3c:4d:5e\"\n\
+                                     \x20 broadcast: 192.168.1.255\n\
+                                     \x20 port: 7\n\
+                                     nas:\n\
+                                     \x20 mac: 00-11-22-33-44-55\n\
+                                     \x20 broadcast: nas.lan\n")
+            .unwrap();
+        assert_eq!(toml.len(), 2);
+        assert_eq!(yaml, toml);
+
+        let path = env::temp_dir().join(format!("wol-hosts-test-{}.yml", std::process::id()));
+        fs::write(&path, "nas:\n  mac: 00-11-22-33-44-55\n  broadcast: nas.lan\n").unwrap();
+        assert_eq!(load_hosts(&path).unwrap()["nas"], toml["nas"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn return_error_for_invalid_hosts_file() {
+        assert!(parse_hosts("[officepc]\nmac = \"zz\"\nbroadcast = \"192.168.1.255\"\n").is_err());
+        assert!(parse_hosts("[officepc]\nbroadcast = \"192.168.1.255\"\n").is_err());
+    }
+
+    #[test]
+    fn can_parse_config() {
+        let config = parse_config("port = 7\n\
+                                   broadcast = \"192.168.1.255\"\n\
+                                   \n\
+                                   [hosts.officepc]\n\
+                                   mac = \"00:1a:2b:3c:4d:5e\"\n\
+                                   broadcast = \"192.168.1.255\"\n")
+            .unwrap();
+
+        assert_eq!(config.port, Some(7));
+        assert_eq!(config.broadcast, Some("192.168.1.255".to_string()));
+        assert_eq!(config.hosts["officepc"].mac, Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e)));
+        assert_eq!(parse_config("").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn return_error_for_missing_explicit_config() {
+        let err = load_config_from(Some(Path::new("/nonexistent/wol.toml")), &[]).unwrap_err();
+        assert_eq!(err.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn can_search_for_config() {
+        let dir = env::temp_dir().join(format!("wol-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.toml");
+        let second = dir.join("second.toml");
+        fs::write(&first, "port = 7\n").unwrap();
+        fs::write(&second, "port = 9\n").unwrap();
+
+        let search: Vec<PathBuf> = vec![dir.join("missing.toml"), first, second];
+        assert_eq!(load_config_from(None, &search).unwrap().port, Some(7));
+        assert_eq!(load_config_from(None, &search[..1]).unwrap(), Config::default());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct HostEntry {
+    pub mac: Mac,
+    pub broadcast: String,
+    pub port: Option<u16>,
+}
+
+pub type Hosts = HashMap<String, HostEntry>;
+
+pub fn parse_hosts(s: &str) -> Result<Hosts, Box<dyn Error>> {
+    Ok(toml::from_str(s)?)
+}
+
+#[cfg(feature = "yaml")]
+pub fn parse_hosts_yaml(s: &str) -> Result<Hosts, Box<dyn Error>> {
+    Ok(serde_yaml::from_str(s)?)
+}
+
+pub fn load_hosts<P: AsRef<Path>>(path: P) -> Result<Hosts, Box<dyn Error>> {
+    let path = path.as_ref();
+    let s = fs::read_to_string(path)?;
+
+    match path.extension().and_then(|e| e.to_str()) {
+        #[cfg(feature = "yaml")]
+        Some("yaml") | Some("yml") => parse_hosts_yaml(&s),
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml") | Some("yml") => Err("YAML hosts files need the yaml feature".into()),
+        _ => parse_hosts(&s),
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+pub struct Config {
+    pub port: Option<u16>,
+    pub broadcast: Option<String>,
+    #[serde(default)]
+    pub hosts: Hosts,
+}
+
+pub fn parse_config(s: &str) -> Result<Config, Box<dyn Error>> {
+    Ok(toml::from_str(s)?)
+}
+
+pub fn config_search_path() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        paths.push(PathBuf::from(dir).join("wol").join("config.toml"));
+    }
+
+    if let Some(home) = env::var_os("HOME").filter(|h| !h.is_empty()) {
+        paths.push(PathBuf::from(home).join(".config").join("wol").join("config.toml"));
+    }
+
+    paths
+}
+
+pub fn load_config(explicit: Option<&Path>) -> Result<Config, Box<dyn Error>> {
+    load_config_from(explicit, &config_search_path())
+}
+
+pub fn load_config_from(explicit: Option<&Path>,
+                        search: &[PathBuf])
+                        -> Result<Config, Box<dyn Error>> {
+    if let Some(path) = explicit {
+        return parse_config(&fs::read_to_string(path)?);
+    }
+
+    for path in search {
+        match fs::read_to_string(path) {
+            Ok(s) => return parse_config(&s),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(Config::default())
+}
@@ -0,0 +1,1470 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "std")]
+extern crate if_addrs;
+
+#[cfg(feature = "regex")]
+extern crate regex;
+
+#[cfg(all(unix, feature = "std"))]
+extern crate libc;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+
+#[cfg(feature = "config")]
+extern crate toml;
+
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+
+#[cfg(feature = "config")]
+pub mod config;
+
+#[cfg(feature = "std")]
+mod net;
+mod oui;
+
+#[cfg(feature = "std")]
+pub use net::*;
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+#[cfg(feature = "regex")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String, vec, vec::Vec};
+use core::convert::TryFrom;
+use core::error::Error;
+use core::fmt;
+use core::str::FromStr;
+use core::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "alloc")]
+use core::net::SocketAddrV4;
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use super::{broadcast_for_cidr, broadcast_for_netmask, build_ethernet_frame, build_packet,
+                build_packet_array, build_packet_n, build_packet_with_password, build_udp_frame,
+                checksum, hex_dump, is_magic_packet, looks_like_broadcast, magic_packet_len,
+                parse_packet, sweep_broadcasts, LocalInterface, Mac, MacFormat, ParseError,
+                Password, PasswordParseError, WolError};
+    use std::collections::HashSet;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddrV4};
+
+    #[test]
+    fn can_parse_valid_mac() {
+        assert_eq!("ff:ff:ff:ff:ff:ff".parse::<Mac>().unwrap(),
+                   Mac([255, 255, 255, 255, 255, 255]));
+        assert_eq!("FF:FF:FF:FF:FF:FF".parse::<Mac>().unwrap(),
+                   Mac([255, 255, 255, 255, 255, 255]));
+        assert_eq!("00:00:00:00:00:00".parse::<Mac>().unwrap(),
+                   Mac([0, 0, 0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn return_error_for_invalid_mac() {
+        let macs = vec![":::::", "ff:ff:ff:ff:ff:ff:ff"];
+        for m in macs {
+            match m.parse::<Mac>() {
+                Err(e) => assert_eq!(e, ParseError::InvalidInput),
+                Ok(_) => unreachable!(),
+            };
+        }
+    }
+
+    #[test]
+    fn can_parse_mac_with_surrounding_whitespace() {
+        assert_eq!(" ff:ff:ff:ff:ff:ff\n".parse::<Mac>().unwrap(),
+                   Mac([255, 255, 255, 255, 255, 255]));
+        assert_eq!("\t00a0.c914.c829 ".parse::<Mac>().unwrap(),
+                   Mac([0x00, 0xa0, 0xc9, 0x14, 0xc8, 0x29]));
+    }
+
+    #[test]
+    fn return_error_for_internal_whitespace() {
+        let macs = vec!["ff:ff: ff:ff:ff:ff", "ff:ff:ff:ff:ff :ff", "ffffff ffffff"];
+        for m in macs {
+            match m.parse::<Mac>() {
+                Err(e) => assert_eq!(e, ParseError::InvalidInput),
+                Ok(_) => unreachable!(),
+            };
+        }
+    }
+
+    #[test]
+    fn can_parse_dash_separated_mac() {
+        assert_eq!("ff-ff-ff-ff-ff-ff".parse::<Mac>().unwrap(),
+                   Mac([255, 255, 255, 255, 255, 255]));
+        assert_eq!("00-1A-2b-3C-4d-5E".parse::<Mac>().unwrap(),
+                   Mac([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+        assert_eq!("00-1a-2b-3c-4d-5e".parse::<Mac>().unwrap(),
+                   "00:1a:2b:3c:4d:5e".parse::<Mac>().unwrap());
+    }
+
+    #[test]
+    fn return_position_of_invalid_character() {
+        assert_eq!("ff:ff:ff:ff:ff:fg".parse::<Mac>(),
+                   Err(ParseError::InvalidCharacter { index: 16, found: 'g' }));
+        assert_eq!("FFFFFFFFFFFG".parse::<Mac>(),
+                   Err(ParseError::InvalidCharacter { index: 11, found: 'G' }));
+        assert_eq!("00a0.c9z4.c829".parse::<Mac>(),
+                   Err(ParseError::InvalidCharacter { index: 7, found: 'z' }));
+        assert_eq!(ParseError::InvalidCharacter { index: 16, found: 'g' }.to_string(),
+                   "MAC address has an invalid character 'g' at position 16");
+    }
+
+    #[test]
+    fn return_error_for_mixed_separators() {
+        assert_eq!("ff:ff-ff:ff:ff:ff".parse::<Mac>(),
+                   Err(ParseError::InvalidCharacter { index: 5, found: '-' }));
+        assert_eq!("ff-ff-ff-ff-ff:ff".parse::<Mac>(),
+                   Err(ParseError::InvalidCharacter { index: 14, found: ':' }));
+    }
+
+    #[test]
+    fn can_parse_cisco_mac() {
+        assert_eq!("00a0.c914.c829".parse::<Mac>().unwrap(),
+                   Mac([0x00, 0xa0, 0xc9, 0x14, 0xc8, 0x29]));
+        assert_eq!("ffff.ffff.ffff".parse::<Mac>().unwrap(),
+                   "ff:ff:ff:ff:ff:ff".parse::<Mac>().unwrap());
+    }
+
+    #[test]
+    fn return_error_for_invalid_cisco_mac() {
+        let macs = vec!["00a0.c914.c82",
+                        "00a0.c914",
+                        "00a0.c914.c829.0000",
+                        "00a0c914.c829"];
+        for m in macs {
+            match m.parse::<Mac>() {
+                Err(e) => assert_eq!(e, ParseError::InvalidInput),
+                Ok(_) => unreachable!(),
+            };
+        }
+    }
+
+    #[test]
+    fn can_parse_bare_mac() {
+        assert_eq!("FFFFFFFFFFFF".parse::<Mac>().unwrap(),
+                   Mac([255, 255, 255, 255, 255, 255]));
+        assert_eq!("001a2B3c4D5e".parse::<Mac>().unwrap(),
+                   Mac([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+    }
+
+    #[test]
+    fn can_parse_mac_leniently() {
+        let mac = Mac::broadcast();
+        assert_eq!(Mac::parse_lenient("ff ff ff ff ff ff"), Ok(mac));
+        assert_eq!(Mac::parse_lenient("ff.ff.ff.ff.ff.ff"), Ok(mac));
+        assert_eq!(Mac::parse_lenient("ff_ff-ff:ff ff.ff"), Ok(mac));
+        assert_eq!(Mac::parse_lenient("ff ff ff ff ff"), Err(ParseError::InvalidLength));
+
+        assert!("ff ff ff ff ff ff".parse::<Mac>().is_err());
+        assert!("ff.ff.ff.ff.ff.ff".parse::<Mac>().is_err());
+    }
+
+    #[test]
+    fn can_parse_many_macs() {
+        let (macs, failures) = Mac::parse_many("00:1a:2b:3c:4d:5e, ff-ff-ff-ff-ff-ff\n\
+                                                001a.2b3c.4d5f,zz:zz\t\t001a2b3c4d60\n");
+        assert_eq!(macs,
+                   vec![Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e)),
+                        Mac::broadcast(),
+                        Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5f)),
+                        Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x60))]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "zz:zz");
+
+        assert_eq!(Mac::parse_many(" ,\n"), (vec![], vec![]));
+    }
+
+    #[test]
+    fn return_error_for_invalid_bare_mac() {
+        let macs = vec!["FFFFFFFFFFF", "FFFFFFFFFFFFF"];
+        for m in macs {
+            match m.parse::<Mac>() {
+                Err(e) => assert_eq!(e, ParseError::InvalidInput),
+                Ok(_) => unreachable!(),
+            };
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn manual_parser_matches_regex_parser() {
+        let inputs = vec!["ff:ff:ff:ff:ff:ff",
+                          "00-1A-2b-3C-4d-5E",
+                          "00a0.c914.c829",
+                          "001a2B3c4D5e",
+                          ":::::",
+                          "ff:ff:ff:ff:ff:fg",
+                          "ff:ff:ff:ff:ff:ff:ff",
+                          "ff:ff-ff:ff:ff:ff",
+                          "ff-ff-ff-ff-ff:ff",
+                          "00a0.c914.c82",
+                          "00a0.c914",
+                          "00a0.c914.c829.0000",
+                          "00a0c914.c829",
+                          "FFFFFFFFFFF",
+                          "FFFFFFFFFFFG",
+                          "FFFFFFFFFFFFF",
+                          "ff:ff:ff:ff:ff:f\u{e9}",
+                          "gf:ff:ff:ff:ff:ff",
+                          "ff:ff:ff:ff:ffxff",
+                          "00a0-c914.c829",
+                          "00a0.c914.c82g",
+                          "zzzzzzzzzzzz",
+                          "ff:ff:ff:ff:ff",
+                          ""];
+
+        let (mut invalid_character, mut invalid_input) = (false, false);
+
+        for m in inputs {
+            let parsed = super::split_octets_regex(m);
+            assert_eq!(super::split_octets(m), parsed, "input {:?}", m);
+
+            match parsed {
+                Err(ParseError::InvalidCharacter { .. }) => invalid_character = true,
+                Err(ParseError::InvalidInput) => invalid_input = true,
+                _ => {}
+            }
+        }
+
+        assert!(invalid_character && invalid_input);
+    }
+
+    #[test]
+    fn can_parse_repeatedly() {
+        for i in 0..1000 {
+            let mac = Mac([0, 0, 0, 0, (i >> 8) as u8, i as u8]);
+            assert_eq!(mac.to_string().parse::<Mac>().unwrap(), mac);
+        }
+    }
+
+    #[test]
+    fn can_convert_mac_from_bytes() {
+        let bytes = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e];
+        assert_eq!(Mac::from(bytes), Mac([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+        assert_eq!(Mac::from(bytes).as_bytes(), bytes);
+    }
+
+    #[test]
+    fn can_get_octets_and_oui() {
+        let mac: Mac = "00:1b:63:84:45:e6".parse().unwrap();
+        assert_eq!(mac.octets(), [0x00, 0x1b, 0x63, 0x84, 0x45, 0xe6]);
+        assert_eq!(mac.oui(), [0x00, 0x1b, 0x63]);
+    }
+
+    #[test]
+    fn can_iterate_over_mac_octets() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.into_iter().collect::<Vec<u8>>(), mac.as_bytes().to_vec());
+        assert_eq!(mac.as_ref(), &mac.as_bytes()[..]);
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn random_mac_is_locally_administered_unicast() {
+        for _ in 0..32 {
+            let mac = Mac::random();
+            assert_eq!(mac.as_bytes()[0] & 0x02, 0x02);
+            assert_eq!(mac.as_bytes()[0] & 0x01, 0);
+        }
+    }
+
+    #[test]
+    fn can_take_mac_prefix() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.prefix(24), Ok(Mac::new((0x00, 0x1a, 0x2b, 0x00, 0x00, 0x00))));
+        assert_eq!(mac.prefix(28), Ok(Mac::new((0x00, 0x1a, 0x2b, 0x30, 0x00, 0x00))));
+        assert_eq!(mac.prefix(48), Ok(mac));
+        assert_eq!(mac.prefix(0), Ok(Mac::new((0, 0, 0, 0, 0, 0))));
+        assert_eq!(mac.prefix(49), Err(ParseError::InvalidMacPrefixLength));
+        assert_eq!(mac.prefix(49).unwrap_err().to_string(),
+                   "MAC prefix length must be between 0 and 48");
+    }
+
+    #[test]
+    fn can_increment_mac() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.increment(), Some(Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5f))));
+
+        let mac: Mac = "00:1a:2b:3c:ff:ff".parse().unwrap();
+        assert_eq!(mac.increment(), Some(Mac::new((0x00, 0x1a, 0x2b, 0x3d, 0x00, 0x00))));
+    }
+
+    #[test]
+    fn return_none_for_increment_past_broadcast() {
+        assert_eq!(Mac::broadcast().increment(), None);
+    }
+
+    #[test]
+    fn can_convert_mac_to_and_from_u64() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.to_u64(), 0x001a_2b3c_4d5e);
+        assert_eq!(Mac::from_u64(mac.to_u64()), Ok(mac));
+        assert_eq!(Mac::from_u64(0xffff_ffff_ffff), Ok(Mac::broadcast()));
+    }
+
+    #[test]
+    fn return_error_for_u64_out_of_range() {
+        assert_eq!(Mac::from_u64(0x1_0000_0000_0000), Err(ParseError::OutOfRange));
+        assert_eq!(Mac::from_u64(u64::MAX), Err(ParseError::OutOfRange));
+    }
+
+    #[test]
+    fn default_mac_is_broadcast() {
+        assert_eq!(Mac::default(), Mac::broadcast());
+        assert_eq!(Mac::broadcast().to_string(), "ff:ff:ff:ff:ff:ff");
+        assert!(Mac::default().is_broadcast());
+        assert!(!"00:1a:2b:3c:4d:5e".parse::<Mac>().unwrap().is_broadcast());
+    }
+
+    #[test]
+    fn can_lookup_mac_vendor() {
+        assert_eq!("00:1b:63:84:45:e6".parse::<Mac>().unwrap().vendor(), Some("Apple"));
+        assert_eq!("02:00:00:00:00:01".parse::<Mac>().unwrap().vendor(), None);
+    }
+
+    #[test]
+    fn can_dedup_macs_in_hash_set() {
+        let mut set = HashSet::new();
+        set.insert("00:1a:2b:3c:4d:5e".parse::<Mac>().unwrap());
+        set.insert("00-1A-2B-3C-4D-5E".parse::<Mac>().unwrap());
+        set.insert("ff:ff:ff:ff:ff:ff".parse::<Mac>().unwrap());
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn can_round_trip_mac_through_serde() {
+        extern crate serde_json;
+
+        let mac = Mac([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+        let json = serde_json::to_string(&mac).unwrap();
+        assert_eq!(json, "\"00:1a:2b:3c:4d:5e\"");
+        assert_eq!(serde_json::from_str::<Mac>(&json).unwrap(), mac);
+        assert!(serde_json::from_str::<Mac>("\"not-a-mac\"").is_err());
+    }
+
+    #[test]
+    fn can_order_macs() {
+        let a: Mac = "00:00:00:00:00:01".parse().unwrap();
+        let b: Mac = "00:00:00:00:00:02".parse().unwrap();
+        let c: Mac = "01:00:00:00:00:00".parse().unwrap();
+        assert!(a < b && b < c);
+
+        let mut macs = vec![c, a, b];
+        macs.sort();
+        assert_eq!(macs, vec![a, b, c]);
+    }
+
+    #[test]
+    fn can_display_mac() {
+        let mac = Mac([0x00, 0x1a, 0x2b, 0x0c, 0x0d, 0x0e]);
+        assert_eq!(mac.to_string(), "00:1a:2b:0c:0d:0e");
+        assert_eq!(Mac([255, 255, 255, 255, 255, 255]).to_string(), "ff:ff:ff:ff:ff:ff");
+        assert_eq!(mac.to_string().parse::<Mac>().unwrap(), mac);
+    }
+
+    #[test]
+    fn can_display_errors() {
+        assert_eq!(WolError::InvalidBufferLength.to_string(),
+                   "MAC address buffer has the wrong length");
+        assert_eq!(WolError::InvalidPacketSize.to_string(),
+                   "magic packet has the wrong size");
+        assert_eq!(WolError::SelfTestFailed.to_string(),
+                   "self test did not receive the magic packet it sent");
+        assert_eq!(WolError::InvalidRepeatCount.to_string(),
+                   "magic packet must repeat the MAC at least once");
+        assert_eq!(ParseError::FailedConversion.to_string(),
+                   "MAC address contains an invalid hex octet");
+        assert_eq!(ParseError::InvalidInput.to_string(),
+                   "MAC address is not in a recognised format");
+        assert_eq!(ParseError::InvalidLength.to_string(),
+                   "MAC address has the wrong number of octets");
+        assert_eq!(ParseError::InvalidNetwork.to_string(),
+                   "subnet is not in the form a.b.c.d/n");
+        assert_eq!(ParseError::InvalidPrefixLength.to_string(),
+                   "subnet prefix length must be between 0 and 32");
+        assert_eq!(ParseError::OutOfRange.to_string(),
+                   "MAC address value does not fit in 48 bits");
+    }
+
+    #[test]
+    fn can_build_magic_packet() {
+        let mac: Mac = "ff:ff:ff:ff:ff:ff".parse().unwrap();
+        assert!(!build_packet(&mac).unwrap().is_empty());
+        assert_eq!(build_packet(&mac).unwrap().len(), 102);
+        assert_eq!(build_packet(&mac).unwrap(), vec![255; 102]);
+    }
+
+    #[test]
+    fn can_build_magic_packet_array() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(build_packet_array(&mac).to_vec(), build_packet(&mac).unwrap());
+    }
+
+    #[test]
+    fn can_build_magic_packet_with_password() {
+        let mac: Mac = "ff:ff:ff:ff:ff:ff".parse().unwrap();
+        let password: Password = "01:02:03:04:05:06".parse().unwrap();
+        let packet = build_packet_with_password(&mac, &password).unwrap();
+        assert_eq!(packet.len(), 108);
+        assert_eq!(packet[..102].to_vec(), build_packet(&mac).unwrap());
+        assert_eq!(&packet[102..], &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn return_password_error_for_invalid_password() {
+        let err = "01:02:03:04:05".parse::<Password>().unwrap_err();
+        assert_eq!(err, PasswordParseError::InvalidLength);
+        assert_eq!(err.to_string(), "password must be exactly six bytes");
+        assert_eq!("01:02:03:04:05:06:07".parse::<Password>(),
+                   Err(PasswordParseError::InvalidLength));
+        assert_eq!("01:02:03:04:05:zz".parse::<Password>(),
+                   Err(PasswordParseError::InvalidCharacter { index: 15, found: 'z' }));
+        assert_eq!("01:02:03:0405:06".parse::<Password>(),
+                   Err(PasswordParseError::InvalidInput));
+        assert!(!"not a password".parse::<Password>().unwrap_err().to_string().contains("MAC"));
+        assert_eq!("010203040506".parse::<Password>(),
+                   Ok(Password::from_octets([1, 2, 3, 4, 5, 6])));
+    }
+
+    #[test]
+    fn can_build_const_mac() {
+        const SERVER: Mac = Mac::from_octets([0, 0, 0, 0, 0, 1]);
+        assert_eq!(SERVER, Mac::new((0, 0, 0, 0, 0, 1)));
+        assert_eq!(SERVER.to_string(), "00:00:00:00:00:01");
+    }
+
+    #[test]
+    fn can_build_mac_from_slice() {
+        let chaddr = [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(Mac::from_slice(&chaddr[..6]),
+                   Ok(Mac::new((0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e))));
+        assert_eq!(Mac::from_slice(&chaddr[..5]), Err(ParseError::InvalidLength));
+        assert_eq!(Mac::from_slice(&chaddr), Err(ParseError::InvalidLength));
+    }
+
+    #[test]
+    fn can_compute_link_local_ipv6() {
+        let mac: Mac = "34:56:78:9a:bc:de".parse().unwrap();
+        assert_eq!(mac.to_link_local_ipv6(),
+                   "fe80::3656:78ff:fe9a:bcde".parse::<Ipv6Addr>().unwrap());
+
+        let mac: Mac = "02:00:5e:10:00:01".parse().unwrap();
+        assert_eq!(mac.to_link_local_ipv6(),
+                   Ipv6Addr::new(0xfe80, 0, 0, 0, 0x0000, 0x5eff, 0xfe10, 0x0001));
+    }
+
+    #[test]
+    fn can_convert_mac_to_and_from_eui64() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let eui = mac.to_eui64();
+        assert_eq!(eui, [0x02, 0x1a, 0x2b, 0xff, 0xfe, 0x3c, 0x4d, 0x5e]);
+        assert_eq!(Mac::from_eui64(eui), Some(mac));
+
+        assert_eq!(Mac::from_eui64([0x02, 0x1a, 0x2b, 0x00, 0x00, 0x3c, 0x4d, 0x5e]), None);
+        assert_eq!(Mac::from_eui64([0x02, 0x1a, 0x2b, 0xfe, 0xff, 0x3c, 0x4d, 0x5e]), None);
+    }
+
+    #[test]
+    fn can_detect_unicast_mac() {
+        let unicast: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert!(unicast.is_unicast());
+
+        let multicast: Mac = "01:00:5e:00:00:01".parse().unwrap();
+        assert!(!multicast.is_unicast());
+        assert!(!multicast.is_broadcast());
+
+        assert!(!Mac::broadcast().is_unicast());
+        assert!(Mac::broadcast().is_broadcast());
+    }
+
+    #[test]
+    fn can_compare_ignoring_laa_bit() {
+        let universal: Mac = "00:1b:63:84:45:e6".parse().unwrap();
+        let local: Mac = "02:1b:63:84:45:e6".parse().unwrap();
+        assert_ne!(universal, local);
+        assert!(universal.eq_ignoring_laa(&local));
+        assert!(local.eq_ignoring_laa(&universal));
+
+        let multicast: Mac = "01:1b:63:84:45:e6".parse().unwrap();
+        assert!(!universal.eq_ignoring_laa(&multicast));
+        assert!(!universal.eq_ignoring_laa(&"00:1b:63:84:45:e7".parse().unwrap()));
+    }
+
+    #[test]
+    fn can_detect_locally_administered_mac() {
+        let universal: Mac = "00:1b:63:84:45:e6".parse().unwrap();
+        assert!(universal.is_universal());
+        assert!(!universal.is_locally_administered());
+
+        let local: Mac = "02:00:5e:10:00:01".parse().unwrap();
+        assert!(local.is_locally_administered());
+        assert!(!local.is_universal());
+    }
+
+    #[test]
+    fn can_format_mac() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.format(MacFormat::LowerColon), "00:1a:2b:3c:4d:5e");
+        assert_eq!(mac.format(MacFormat::UpperColon), "00:1A:2B:3C:4D:5E");
+        assert_eq!(mac.format(MacFormat::LowerDash), "00-1a-2b-3c-4d-5e");
+        assert_eq!(mac.format(MacFormat::Cisco), "001a.2b3c.4d5e");
+        assert_eq!(mac.format(MacFormat::Bare), "001a2b3c4d5e");
+        assert_eq!(mac.to_string(), mac.format(MacFormat::default()));
+
+        assert_eq!("cisco".parse::<MacFormat>(), Ok(MacFormat::Cisco));
+        assert_eq!("Cisco".parse::<MacFormat>(), Err(ParseError::UnknownFormat));
+        assert_eq!(ParseError::UnknownFormat.to_string(),
+                   "MAC format must be lower-colon, upper-colon, lower-dash, cisco or bare");
+    }
+
+    #[test]
+    fn can_compute_magic_packet_len() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(magic_packet_len(false), 102);
+        assert_eq!(magic_packet_len(true), 108);
+        assert_eq!(build_packet(&mac).unwrap().len(), magic_packet_len(false));
+        let password = Password::from_octets(mac.as_bytes());
+        assert_eq!(build_packet_with_password(&mac, &password).unwrap().len(),
+                   magic_packet_len(true));
+    }
+
+    #[test]
+    fn magic_packet_bytes_match_built_packet() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(mac.magic_packet_bytes().collect::<Vec<u8>>(), build_packet(&mac).unwrap());
+        assert_eq!(mac.magic_packet_bytes().count(), 102);
+    }
+
+    #[test]
+    fn can_build_packet_with_repeat_count() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        assert_eq!(build_packet_n(&mac, 16).unwrap(), build_packet(&mac).unwrap());
+
+        let packet = build_packet_n(&mac, 4).unwrap();
+        assert_eq!(packet.len(), 30);
+        assert_eq!(packet[..6], [0xff; 6]);
+        assert!(packet[6..].chunks(6).all(|c| c == mac.as_bytes()));
+
+        assert!(matches!(build_packet_n(&mac, 0), Err(WolError::InvalidRepeatCount)));
+    }
+
+    #[test]
+    fn can_hex_dump_packet() {
+        assert_eq!(hex_dump(&[0xff; 18]),
+                   "ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\nff ff\n");
+
+        let dump = hex_dump(&build_packet(&Mac::broadcast()).unwrap());
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[..6].iter().all(|l| l.split(' ').count() == 16));
+        assert_eq!(lines[6].split(' ').count(), 6);
+    }
+
+    #[test]
+    fn can_build_ethernet_frame() {
+        let dst: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let src: Mac = "02:00:00:00:00:01".parse().unwrap();
+        let packet = build_packet(&dst).unwrap();
+        let frame = build_ethernet_frame(&dst, &src, &packet);
+
+        assert_eq!(frame.len(), 14 + 102);
+        assert_eq!(frame[0..6], dst.as_bytes());
+        assert_eq!(frame[6..12], src.as_bytes());
+        assert_eq!(frame[12..14], [0x08, 0x42]);
+        assert_eq!(frame[14..], packet[..]);
+    }
+
+    #[test]
+    fn can_build_udp_frame() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let packet = build_packet(&mac).unwrap();
+        let src = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 20), 40000);
+        let dst = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 255), 9);
+        let frame = build_udp_frame(src, dst, &packet);
+
+        assert_eq!(frame.len(), 14 + 20 + 8 + 102);
+        assert_eq!(frame[0..6], [0xff; 6]);
+        assert_eq!(frame[12..14], [0x08, 0x00]);
+        assert_eq!(frame[14], 0x45);
+        assert_eq!(frame[16..18], 130u16.to_be_bytes());
+        assert_eq!(frame[23], 17);
+        assert_eq!(checksum(&frame[14..34]), 0);
+        assert_eq!(frame[26..30], [192, 168, 1, 20]);
+        assert_eq!(frame[30..34], [192, 168, 1, 255]);
+        assert_eq!(frame[34..36], 40000u16.to_be_bytes());
+        assert_eq!(frame[36..38], 9u16.to_be_bytes());
+        assert_eq!(frame[38..40], 110u16.to_be_bytes());
+        assert_eq!(frame[42..], packet[..]);
+        assert_eq!(parse_packet(&frame[42..]), Some(mac));
+    }
+
+    #[test]
+    fn can_compute_broadcast_for_cidr() {
+        assert_eq!(broadcast_for_cidr("192.168.1.0/24").unwrap(),
+                   Ipv4Addr::new(192, 168, 1, 255));
+        assert_eq!(broadcast_for_cidr("192.168.1.0/25").unwrap(),
+                   Ipv4Addr::new(192, 168, 1, 127));
+        assert_eq!(broadcast_for_cidr("192.168.1.130/25").unwrap(),
+                   Ipv4Addr::new(192, 168, 1, 255));
+        assert_eq!(broadcast_for_cidr("10.0.0.1/32").unwrap(), Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(broadcast_for_cidr("10.0.0.1/0").unwrap(),
+                   Ipv4Addr::new(255, 255, 255, 255));
+    }
+
+    #[test]
+    fn can_compute_broadcast_for_netmask() {
+        assert_eq!(broadcast_for_netmask(Ipv4Addr::new(10, 0, 0, 7),
+                                         Ipv4Addr::new(255, 255, 255, 0)),
+                   Ipv4Addr::new(10, 0, 0, 255));
+        assert_eq!(broadcast_for_netmask(Ipv4Addr::new(172, 16, 9, 1),
+                                         Ipv4Addr::new(255, 255, 240, 0)),
+                   Ipv4Addr::new(172, 16, 15, 255));
+        assert_eq!(broadcast_for_netmask(Ipv4Addr::new(10, 0, 0, 7),
+                                         Ipv4Addr::new(255, 255, 255, 255)),
+                   Ipv4Addr::new(10, 0, 0, 7));
+    }
+
+    #[test]
+    fn can_compute_interface_broadcast() {
+        let eth0 = LocalInterface {
+            name: "eth0".to_string(),
+            addr: Ipv4Addr::new(192, 168, 1, 20),
+            netmask: Ipv4Addr::new(255, 255, 255, 0),
+        };
+        assert_eq!(eth0.broadcast(), Ipv4Addr::new(192, 168, 1, 255));
+
+        let wlan0 = LocalInterface {
+            name: "wlan0".to_string(),
+            addr: Ipv4Addr::new(10, 0, 5, 9),
+            netmask: Ipv4Addr::new(255, 255, 252, 0),
+        };
+        assert_eq!(wlan0.broadcast(), Ipv4Addr::new(10, 0, 7, 255));
+    }
+
+    #[test]
+    fn return_error_for_invalid_cidr() {
+        assert_eq!(broadcast_for_cidr("192.168.1.0/40"), Err(ParseError::InvalidPrefixLength));
+        assert_eq!(broadcast_for_cidr("192.168.1.0/x"), Err(ParseError::InvalidPrefixLength));
+        assert_eq!(broadcast_for_cidr("192.168.1.0"), Err(ParseError::InvalidNetwork));
+        assert_eq!(broadcast_for_cidr("192.168.1/24"), Err(ParseError::InvalidNetwork));
+    }
+
+    #[test]
+    fn can_sweep_broadcasts_in_supernet() {
+        let broadcasts: Vec<Ipv4Addr> = sweep_broadcasts("192.168.0.0/22").unwrap().collect();
+        assert_eq!(broadcasts,
+                   vec![Ipv4Addr::new(192, 168, 0, 255),
+                        Ipv4Addr::new(192, 168, 1, 255),
+                        Ipv4Addr::new(192, 168, 2, 255),
+                        Ipv4Addr::new(192, 168, 3, 255)]);
+
+        assert_eq!(sweep_broadcasts("10.1.2.3/24").unwrap().collect::<Vec<_>>(),
+                   vec![Ipv4Addr::new(10, 1, 2, 255)]);
+        assert_eq!(sweep_broadcasts("10.0.0.0/8").unwrap().len(), 65536);
+        assert!(sweep_broadcasts("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn can_tell_broadcast_from_unicast() {
+        assert!(looks_like_broadcast(&Ipv4Addr::new(255, 255, 255, 255)));
+        assert!(looks_like_broadcast(&Ipv4Addr::new(192, 168, 1, 255)));
+        assert!(looks_like_broadcast(&Ipv4Addr::new(224, 0, 0, 1)));
+        assert!(!looks_like_broadcast(&Ipv4Addr::new(192, 168, 1, 20)));
+    }
+
+    #[test]
+    fn can_parse_magic_packet() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let password: Password = "01:02:03:04:05:06".parse().unwrap();
+        assert_eq!(parse_packet(&build_packet(&mac).unwrap()), Some(mac));
+        assert_eq!(parse_packet(&build_packet_with_password(&mac, &password).unwrap()),
+                   Some(mac));
+    }
+
+    #[test]
+    fn return_none_for_invalid_magic_packet() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let packet = build_packet(&mac).unwrap();
+
+        let mut bad_header = packet.clone();
+        bad_header[0] = 0;
+        let mut bad_repeat = packet.clone();
+        bad_repeat[101] = 0;
+
+        assert_eq!(parse_packet(&bad_header), None);
+        assert_eq!(parse_packet(&bad_repeat), None);
+        assert_eq!(parse_packet(&packet[..101]), None);
+        assert_eq!(parse_packet(&[]), None);
+    }
+
+    #[test]
+    fn can_validate_magic_packet() {
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let password: Password = "01:02:03:04:05:06".parse().unwrap();
+        let packet = build_packet(&mac).unwrap();
+        assert!(is_magic_packet(&packet));
+        assert!(is_magic_packet(&build_packet_with_password(&mac, &password).unwrap()));
+
+        let mut mismatched = packet.clone();
+        mismatched[50] ^= 0x01;
+        assert!(!is_magic_packet(&mismatched));
+
+        let mut too_long = packet.clone();
+        too_long.push(0);
+        assert!(!is_magic_packet(&too_long));
+        assert!(!is_magic_packet(&packet[..96]));
+    }
+
+}
+
+#[derive(Debug)]
+pub enum WolError {
+    InvalidBufferLength,
+    InvalidPacketSize,
+    InvalidRepeatCount,
+    SelfTestFailed,
+    // send_to reported fewer bytes than the packet holds.
+    ShortWrite { expected: usize, actual: usize },
+    WaitTimedOut,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    FailedConversion,
+    InvalidCharacter { index: usize, found: char },
+    InvalidInput,
+    InvalidLength,
+    InvalidMacPrefixLength,
+    InvalidNetwork,
+    InvalidPrefixLength,
+    OutOfRange,
+    UnknownFormat,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PasswordParseError {
+    InvalidCharacter { index: usize, found: char },
+    InvalidInput,
+    InvalidLength,
+}
+
+impl fmt::Display for WolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            WolError::InvalidBufferLength => "MAC address buffer has the wrong length",
+            WolError::InvalidPacketSize => "magic packet has the wrong size",
+            WolError::InvalidRepeatCount => "magic packet must repeat the MAC at least once",
+            WolError::SelfTestFailed => "self test did not receive the magic packet it sent",
+            WolError::ShortWrite { expected, actual } => {
+                return write!(f, "short write: sent {} of {} bytes", actual, expected)
+            }
+            WolError::WaitTimedOut => "timed out waiting for host to come online",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for WolError {}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            ParseError::InvalidCharacter { index, found } => {
+                return write!(f,
+                              "MAC address has an invalid character {:?} at position {}",
+                              found,
+                              index)
+            }
+            ParseError::FailedConversion => "MAC address contains an invalid hex octet",
+            ParseError::InvalidInput => "MAC address is not in a recognised format",
+            ParseError::InvalidLength => "MAC address has the wrong number of octets",
+            ParseError::InvalidMacPrefixLength => "MAC prefix length must be between 0 and 48",
+            ParseError::InvalidNetwork => "subnet is not in the form a.b.c.d/n",
+            ParseError::InvalidPrefixLength => "subnet prefix length must be between 0 and 32",
+            ParseError::OutOfRange => "MAC address value does not fit in 48 bits",
+            ParseError::UnknownFormat => {
+                "MAC format must be lower-colon, upper-colon, lower-dash, cisco or bare"
+            }
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for ParseError {}
+
+impl fmt::Display for PasswordParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            PasswordParseError::InvalidCharacter { index, found } => {
+                return write!(f,
+                              "password has an invalid character {:?} at position {}",
+                              found,
+                              index)
+            }
+            PasswordParseError::InvalidInput => "password is not in the form FF:FF:FF:FF:FF:FF",
+            PasswordParseError::InvalidLength => "password must be exactly six bytes",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for PasswordParseError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Mac([u8; 6]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MacFormat {
+    #[default]
+    LowerColon,
+    UpperColon,
+    LowerDash,
+    Cisco,
+    Bare,
+}
+
+impl Mac {
+    pub fn new(a: (u8, u8, u8, u8, u8, u8)) -> Mac {
+        Mac([a.0, a.1, a.2, a.3, a.4, a.5])
+    }
+
+    pub const fn from_octets(octets: [u8; 6]) -> Mac {
+        Mac(octets)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Mac, ParseError> {
+        match <[u8; 6]>::try_from(bytes) {
+            Ok(octets) => Ok(Mac(octets)),
+            Err(_) => Err(ParseError::InvalidLength),
+        }
+    }
+
+    pub fn broadcast() -> Mac {
+        Mac([0xff, 0xff, 0xff, 0xff, 0xff, 0xff])
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn random() -> Mac {
+        let mut b: [u8; 6] = rand::random();
+        b[0] = (b[0] | 0x02) & !0x01;
+        Mac(b)
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        *self == Mac::broadcast()
+    }
+
+    pub fn is_unicast(&self) -> bool {
+        self.0[0] & 0x01 == 0
+    }
+
+    pub fn is_locally_administered(&self) -> bool {
+        self.0[0] & 0x02 != 0
+    }
+
+    pub fn is_universal(&self) -> bool {
+        !self.is_locally_administered()
+    }
+
+    pub fn eq_ignoring_laa(&self, other: &Mac) -> bool {
+        self.0[0] | 0x02 == other.0[0] | 0x02 && self.0[1..] == other.0[1..]
+    }
+
+    pub fn magic_packet_bytes(&self) -> impl Iterator<Item = u8> {
+        let octets = self.0;
+
+        core::iter::repeat_n(0xff, 6).chain((0..16).flat_map(move |_| octets))
+    }
+
+    pub fn parse_lenient(s: &str) -> Result<Mac, ParseError> {
+        let mut octets = [0; 6];
+        let mut digits = 0;
+
+        for d in s.chars().filter_map(|c| c.to_digit(16)) {
+            if digits == 12 {
+                return Err(ParseError::InvalidLength);
+            }
+            octets[digits / 2] = octets[digits / 2] << 4 | d as u8;
+            digits += 1;
+        }
+
+        if digits != 12 {
+            return Err(ParseError::InvalidLength);
+        }
+
+        Ok(Mac(octets))
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn parse_many(input: &str) -> (Vec<Mac>, Vec<(String, ParseError)>) {
+        let mut macs = Vec::new();
+        let mut failures = Vec::new();
+
+        let tokens = input.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty());
+
+        for token in tokens {
+            match token.parse() {
+                Ok(mac) => macs.push(mac),
+                Err(e) => failures.push((String::from(token), e)),
+            }
+        }
+
+        (macs, failures)
+    }
+
+    pub fn from_u64(n: u64) -> Result<Mac, ParseError> {
+        if n > 0xffff_ffff_ffff {
+            return Err(ParseError::OutOfRange);
+        }
+
+        let b = n.to_be_bytes();
+        Ok(Mac([b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    pub fn to_u64(&self) -> u64 {
+        self.0.iter().fold(0, |n, &b| n << 8 | b as u64)
+    }
+
+    pub fn increment(&self) -> Option<Mac> {
+        Mac::from_u64(self.to_u64() + 1).ok()
+    }
+
+    pub fn prefix(&self, bits: u8) -> Result<Mac, ParseError> {
+        if bits > 48 {
+            return Err(ParseError::InvalidMacPrefixLength);
+        }
+
+        let mask = 0xffff_ffff_ffff & !u64::MAX.checked_shr(16 + bits as u32).unwrap_or(0);
+        Mac::from_u64(self.to_u64() & mask)
+    }
+
+    pub fn as_bytes(&self) -> [u8; 6] {
+        self.0
+    }
+
+    pub fn octets(&self) -> [u8; 6] {
+        self.as_bytes()
+    }
+
+    pub fn from_eui64(eui: [u8; 8]) -> Option<Mac> {
+        if eui[3] != 0xff || eui[4] != 0xfe {
+            return None;
+        }
+
+        Some(Mac([eui[0] ^ 0x02, eui[1], eui[2], eui[5], eui[6], eui[7]]))
+    }
+
+    pub fn to_eui64(&self) -> [u8; 8] {
+        let b = self.0;
+        [b[0] ^ 0x02, b[1], b[2], 0xff, 0xfe, b[3], b[4], b[5]]
+    }
+
+    pub fn to_link_local_ipv6(&self) -> Ipv6Addr {
+        let mut octets = [0; 16];
+        octets[..2].copy_from_slice(&[0xfe, 0x80]);
+        octets[8..].copy_from_slice(&self.to_eui64());
+        Ipv6Addr::from(octets)
+    }
+
+    pub fn oui(&self) -> [u8; 3] {
+        [self.0[0], self.0[1], self.0[2]]
+    }
+
+    pub fn vendor(&self) -> Option<&'static str> {
+        oui::lookup(self.oui())
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn format(&self, style: MacFormat) -> String {
+        let mut s = String::new();
+        let _ = self.write_formatted(&mut s, style);
+        s
+    }
+
+    fn write_formatted<W: fmt::Write>(&self, w: &mut W, style: MacFormat) -> fmt::Result {
+        let (sep, group) = match style {
+            MacFormat::LowerColon | MacFormat::UpperColon => (Some(':'), 1),
+            MacFormat::LowerDash => (Some('-'), 1),
+            MacFormat::Cisco => (Some('.'), 2),
+            MacFormat::Bare => (None, 1),
+        };
+
+        for (i, b) in self.0.iter().enumerate() {
+            match sep {
+                Some(sep) if i > 0 && i % group == 0 => w.write_char(sep)?,
+                _ => {}
+            }
+            match style {
+                MacFormat::UpperColon => write!(w, "{:02X}", b)?,
+                _ => write!(w, "{:02x}", b)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AsRef<[u8]> for Mac {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl IntoIterator for Mac {
+    type Item = u8;
+    type IntoIter = core::array::IntoIter<u8, 6>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIterator::into_iter(self.0)
+    }
+}
+
+impl Default for Mac {
+    fn default() -> Mac {
+        Mac::broadcast()
+    }
+}
+
+impl From<[u8; 6]> for Mac {
+    fn from(b: [u8; 6]) -> Mac {
+        Mac(b)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Password([u8; 6]);
+
+impl Password {
+    pub const fn from_octets(octets: [u8; 6]) -> Password {
+        Password(octets)
+    }
+
+    pub fn as_bytes(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl FromStr for Password {
+    type Err = PasswordParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let sep = s.chars().find(|&c| c == ':' || c == '-');
+
+        if let Some((index, found)) = s.char_indices()
+            .find(|&(_, c)| !c.is_ascii_hexdigit() && Some(c) != sep) {
+            return Err(PasswordParseError::InvalidCharacter { index, found });
+        }
+
+        let mut password = [0; 6];
+        let mut n = 0;
+
+        match sep {
+            Some(sep) => {
+                for part in s.split(sep) {
+                    if n == password.len() {
+                        return Err(PasswordParseError::InvalidLength);
+                    }
+                    if part.len() != 2 {
+                        return Err(PasswordParseError::InvalidInput);
+                    }
+                    password[n] = u8::from_str_radix(part, 16)
+                        .map_err(|_| PasswordParseError::InvalidInput)?;
+                    n += 1;
+                }
+            }
+            None => {
+                if s.len() != 2 * password.len() {
+                    return Err(PasswordParseError::InvalidLength);
+                }
+                for (i, b) in password.iter_mut().enumerate() {
+                    *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16)
+                        .map_err(|_| PasswordParseError::InvalidInput)?;
+                }
+                n = password.len();
+            }
+        }
+
+        if n == password.len() {
+            Ok(Password(password))
+        } else {
+            Err(PasswordParseError::InvalidLength)
+        }
+    }
+}
+
+impl fmt::Display for Mac {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_formatted(f, MacFormat::LowerColon)
+    }
+}
+
+#[cfg(feature = "regex")]
+fn split_octets_regex(s: &str) -> Result<[&str; 6], ParseError> {
+    static VALID_MAC: OnceLock<Regex> = OnceLock::new();
+    static VALID_CISCO: OnceLock<Regex> = OnceLock::new();
+    static VALID_BARE: OnceLock<Regex> = OnceLock::new();
+
+    let valid_mac = VALID_MAC.get_or_init(|| {
+        Regex::new("^([0-9A-Fa-f]{2}:){5}([0-9A-Fa-f]{2})$|\
+                    ^([0-9A-Fa-f]{2}-){5}([0-9A-Fa-f]{2})$")
+            .unwrap()
+    });
+
+    let valid_cisco = VALID_CISCO.get_or_init(|| {
+        Regex::new("^([0-9A-Fa-f]{4}\\.){2}([0-9A-Fa-f]{4})$").unwrap()
+    });
+
+    let valid_bare = VALID_BARE.get_or_init(|| Regex::new("^[0-9A-Fa-f]{12}$").unwrap());
+
+    if valid_mac.is_match(s) || valid_cisco.is_match(s) || valid_bare.is_match(s) {
+        return Ok(octets_at(s));
+    }
+
+    // The longest prefix that could still grow into the layout this length implies
+    // ends at the first bad character.
+    static PREFIXES: OnceLock<[Regex; 4]> = OnceLock::new();
+
+    let prefixes = PREFIXES.get_or_init(|| {
+        ["^([0-9A-Fa-f]{2}:){0,5}[0-9A-Fa-f]{0,2}",
+         "^([0-9A-Fa-f]{2}-){0,5}[0-9A-Fa-f]{0,2}",
+         "^([0-9A-Fa-f]{4}\\.){0,2}[0-9A-Fa-f]{0,4}",
+         "^[0-9A-Fa-f]{0,12}"]
+            .map(|p| Regex::new(p).unwrap())
+    });
+
+    let prefix = match (s.len(), s.as_bytes().get(2)) {
+        (17, Some(b'-')) => &prefixes[1],
+        (17, _) => &prefixes[0],
+        (14, _) => &prefixes[2],
+        (12, _) => &prefixes[3],
+        _ => return Err(ParseError::InvalidInput),
+    };
+
+    let index = prefix.find(s).map_or(0, |m| m.end());
+
+    match s[index..].chars().next() {
+        Some(found) => Err(ParseError::InvalidCharacter { index, found }),
+        None => Err(ParseError::InvalidInput),
+    }
+}
+
+#[cfg(any(not(feature = "regex"), test))]
+fn split_octets(s: &str) -> Result<[&str; 6], ParseError> {
+    let b = s.as_bytes();
+
+    let (seps, sep): (&[usize], u8) = match b.len() {
+        17 if b[2] == b'-' => (&[2, 5, 8, 11, 14], b'-'),
+        17 => (&[2, 5, 8, 11, 14], b':'),
+        14 => (&[4, 9], b'.'),
+        12 => (&[], 0),
+        _ => return Err(ParseError::InvalidInput),
+    };
+
+    let bad = (0..b.len()).find(|&i| {
+        if seps.contains(&i) {
+            b[i] != sep
+        } else {
+            !b[i].is_ascii_hexdigit()
+        }
+    });
+
+    if let Some(index) = bad {
+        let found = s[index..].chars().next().unwrap_or_default();
+        return Err(ParseError::InvalidCharacter { index, found });
+    }
+
+    Ok(octets_at(s))
+}
+
+fn octets_at(s: &str) -> [&str; 6] {
+    let starts = match s.len() {
+        17 => [0, 3, 6, 9, 12, 15],
+        14 => [0, 2, 5, 7, 10, 12],
+        _ => [0, 2, 4, 6, 8, 10],
+    };
+
+    starts.map(|i| &s[i..i + 2])
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Mac {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Mac {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Mac, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Mac {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        #[cfg(feature = "regex")]
+        let octets = split_octets_regex(s)?;
+
+        #[cfg(not(feature = "regex"))]
+        let octets = split_octets(s)?;
+
+        let mut mac = [0; 6];
+        for (b, o) in mac.iter_mut().zip(octets.iter()) {
+            *b = u8::from_str_radix(o, 16).map_err(|_| ParseError::FailedConversion)?;
+        }
+
+        Ok(Mac(mac))
+    }
+}
+
+impl FromStr for MacFormat {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lower-colon" => Ok(MacFormat::LowerColon),
+            "upper-colon" => Ok(MacFormat::UpperColon),
+            "lower-dash" => Ok(MacFormat::LowerDash),
+            "cisco" => Ok(MacFormat::Cisco),
+            "bare" => Ok(MacFormat::Bare),
+            _ => Err(ParseError::UnknownFormat),
+        }
+    }
+}
+
+pub const fn magic_packet_len(with_password: bool) -> usize {
+    let len = 6 + 16 * 6;
+
+    if with_password {
+        len + 6
+    } else {
+        len
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub fn build_packet(mac: &Mac) -> Result<Vec<u8>, WolError> {
+    build_packet_n(mac, 16)
+}
+
+#[cfg(feature = "alloc")]
+pub fn build_packet_n(mac: &Mac, repeats: usize) -> Result<Vec<u8>, WolError> {
+    if repeats == 0 {
+        return Err(WolError::InvalidRepeatCount);
+    }
+
+    let mut packet = vec![0xff; 6];
+    let payload = mac.as_bytes();
+
+    match payload.len() {
+        6 => {
+            for _ in 0..repeats {
+                packet.extend_from_slice(&payload);
+            }
+        }
+        _ => return Err(WolError::InvalidBufferLength),
+    }
+
+    if packet.len() == 6 + repeats * 6 {
+        Ok(packet)
+    } else {
+        Err(WolError::InvalidPacketSize)
+    }
+}
+
+pub fn build_packet_array(mac: &Mac) -> [u8; magic_packet_len(false)] {
+    let mut packet = [0xff; magic_packet_len(false)];
+    let payload = mac.as_bytes();
+
+    for chunk in packet[6..].chunks_mut(6) {
+        chunk.copy_from_slice(&payload);
+    }
+
+    packet
+}
+
+#[cfg(feature = "alloc")]
+pub fn build_packet_with_password(mac: &Mac,
+                                  password: &Password)
+                                  -> Result<Vec<u8>, WolError> {
+    let mut packet = build_packet(mac)?;
+    packet.extend_from_slice(&password.as_bytes());
+
+    if packet.len() == magic_packet_len(true) {
+        Ok(packet)
+    } else {
+        Err(WolError::InvalidPacketSize)
+    }
+}
+
+pub fn parse_packet(buf: &[u8]) -> Option<Mac> {
+    if buf.len() != magic_packet_len(false) && buf.len() != magic_packet_len(true) {
+        return None;
+    }
+
+    if buf[..6] != [0xff; 6] {
+        return None;
+    }
+
+    let payload = &buf[6..12];
+
+    if buf[6..magic_packet_len(false)].chunks(6).all(|c| c == payload) {
+        Some(Mac([payload[0], payload[1], payload[2], payload[3], payload[4], payload[5]]))
+    } else {
+        None
+    }
+}
+
+pub fn is_magic_packet(buf: &[u8]) -> bool {
+    parse_packet(buf).is_some()
+}
+
+#[cfg(feature = "alloc")]
+pub fn hex_dump(buf: &[u8]) -> String {
+    buf.chunks(16)
+        .map(|line| {
+            let bytes: Vec<String> = line.iter().map(|b| format!("{:02x}", b)).collect();
+            bytes.join(" ") + "\n"
+        })
+        .collect()
+}
+
+pub const ETHERTYPE_WOL: u16 = 0x0842;
+pub const ETHERTYPE_IPV4: u16 = 0x0800;
+
+#[cfg(feature = "alloc")]
+pub fn build_ethernet_frame(dst: &Mac, src: &Mac, payload: &[u8]) -> Vec<u8> {
+    ethernet_frame(dst, src, ETHERTYPE_WOL, payload)
+}
+
+#[cfg(feature = "alloc")]
+fn ethernet_frame(dst: &Mac, src: &Mac, ethertype: u16, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(14 + payload.len());
+
+    frame.extend_from_slice(&dst.as_bytes());
+    frame.extend_from_slice(&src.as_bytes());
+    frame.extend_from_slice(&ethertype.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+#[cfg(feature = "alloc")]
+pub fn build_udp_frame(src: SocketAddrV4, dst: SocketAddrV4, payload: &[u8]) -> Vec<u8> {
+    let udp_len = 8 + payload.len();
+    let mut ip = Vec::with_capacity(20 + udp_len);
+
+    ip.extend_from_slice(&[0x45, 0]);
+    ip.extend_from_slice(&((20 + udp_len) as u16).to_be_bytes());
+    ip.extend_from_slice(&[0, 0, 0x40, 0, 64, 17, 0, 0]);
+    ip.extend_from_slice(&src.ip().octets());
+    ip.extend_from_slice(&dst.ip().octets());
+    let header = checksum(&ip);
+    ip[10..12].copy_from_slice(&header.to_be_bytes());
+
+    ip.extend_from_slice(&src.port().to_be_bytes());
+    ip.extend_from_slice(&dst.port().to_be_bytes());
+    ip.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    ip.extend_from_slice(&[0, 0]);
+    ip.extend_from_slice(payload);
+
+    let mut pseudo = Vec::with_capacity(12 + udp_len);
+    pseudo.extend_from_slice(&ip[12..20]);
+    pseudo.extend_from_slice(&[0, 17]);
+    pseudo.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    pseudo.extend_from_slice(&ip[20..]);
+    let udp = match checksum(&pseudo) {
+        0 => 0xffff,
+        sum => sum,
+    };
+    ip[26..28].copy_from_slice(&udp.to_be_bytes());
+
+    ethernet_frame(&Mac::broadcast(), &Mac([0; 6]), ETHERTYPE_IPV4, &ip)
+}
+
+#[cfg(feature = "alloc")]
+fn checksum(buf: &[u8]) -> u16 {
+    let mut sum = buf.chunks(2)
+        .map(|c| u32::from(c[0]) << 8 | u32::from(*c.get(1).unwrap_or(&0)))
+        .sum::<u32>();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+fn parse_cidr(cidr: &str) -> Result<(Ipv4Addr, u32), ParseError> {
+    let (network, prefix) = match cidr.split_once('/') {
+        Some(parts) => parts,
+        None => return Err(ParseError::InvalidNetwork),
+    };
+
+    let network: Ipv4Addr = network.parse().map_err(|_| ParseError::InvalidNetwork)?;
+
+    let prefix: u32 = match prefix.parse() {
+        Ok(p) if p <= 32 => p,
+        _ => return Err(ParseError::InvalidPrefixLength),
+    };
+
+    Ok((network, prefix))
+}
+
+pub fn broadcast_for_cidr(cidr: &str) -> Result<Ipv4Addr, ParseError> {
+    let (network, prefix) = parse_cidr(cidr)?;
+    let host_bits = u32::MAX.checked_shr(prefix).unwrap_or(0);
+
+    Ok(Ipv4Addr::from(u32::from(network) | host_bits))
+}
+
+pub fn broadcast_for_netmask(addr: Ipv4Addr, netmask: Ipv4Addr) -> Ipv4Addr {
+    Ipv4Addr::from(u32::from(addr) | !u32::from(netmask))
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalInterface {
+    pub name: String,
+    pub addr: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+}
+
+#[cfg(feature = "alloc")]
+impl LocalInterface {
+    pub fn broadcast(&self) -> Ipv4Addr {
+        broadcast_for_netmask(self.addr, self.netmask)
+    }
+}
+
+pub fn sweep_broadcasts(cidr: &str)
+                        -> Result<impl ExactSizeIterator<Item = Ipv4Addr>, ParseError> {
+    let (network, prefix) = parse_cidr(cidr)?;
+    let prefix = prefix.min(24);
+    let start = u32::from(network) & !u32::MAX.checked_shr(prefix).unwrap_or(0);
+
+    Ok((0..1u32 << (24 - prefix)).map(move |i| Ipv4Addr::from(start | i << 8 | 0xff)))
+}
+
+pub fn looks_like_broadcast(addr: &Ipv4Addr) -> bool {
+    addr.is_broadcast() || addr.is_multicast() || addr.octets()[3] == 255
+}
+
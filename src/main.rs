@@ -1,188 +1,1839 @@
+extern crate ctrlc;
+extern crate env_logger;
 extern crate getopts;
+#[macro_use]
+extern crate log;
+extern crate termcolor;
+extern crate wol;
+
+#[cfg(feature = "serve")]
+extern crate tiny_http;
+
+#[cfg(feature = "serve")]
+mod serve;
+
+#[cfg(feature = "tui")]
+extern crate crossterm;
+
+#[cfg(feature = "tui")]
+mod tui;
 
 use std::{env, process};
-use getopts::Options;
-use std::net::{SocketAddrV4, Ipv4Addr};
+use std::collections::HashSet;
+#[cfg(not(feature = "rand"))]
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fs::File;
+#[cfg(not(feature = "rand"))]
+use std::hash::BuildHasher;
+use std::hash::Hash;
+use std::io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write};
+use getopts::{Matches, Options};
+use log::{Level, LevelFilter};
+use std::net::{SocketAddr, SocketAddrV4, IpAddr, Ipv4Addr, ToSocketAddrs, UdpSocket};
+#[cfg(feature = "config")]
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use termcolor::{Ansi, Color, ColorSpec, WriteColor};
+
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(100);
+const DAEMON_INTERVAL: Duration = Duration::from_secs(30);
+const DAEMON_TICK: Duration = Duration::from_millis(100);
+const WAIT_TIMEOUT: Duration = Duration::from_secs(300);
+const WAIT_INTERVAL: Duration = Duration::from_secs(3);
+const MAX_SWEEP: usize = 256;
+const SUCCESS: &str = "wol::success";
+#[cfg(feature = "config")]
+const DEFAULT_HOSTS_FILE: &str = "wol-hosts.toml";
+const DEFAULT_ETHERS_FILE: &str = "/etc/ethers";
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let stdin = io::stdin();
+
+    if let Err(e) = run(&args, stdin.lock(), &mut io::stdout()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run<R: BufRead, W: Write>(args: &[String], stdin: R, out: &mut W) -> Result<(), Box<dyn Error>> {
+    run_with_env(args, stdin, out, &|key| env::var(key).ok())
+}
+
+// Environment variables are looked up through env so tests need not touch the process.
+fn run_with_env<R: BufRead, W: Write>(args: &[String],
+                                      stdin: R,
+                                      out: &mut W,
+                                      env: &dyn Fn(&str) -> Option<String>)
+                                      -> Result<(), Box<dyn Error>> {
+    let opts = options();
 
-mod wol {
-    extern crate regex;
+    let name = args[0].clone();
+
+    let usage = format!("Usage: {}", opts.usage(&(name + " [options]")));
+
+    let matches = opts.parse(&args[1..]).map_err(|e| format!("could not parse args: {}", e))?;
 
-    use wol::regex::Regex;
+    if let Some(shell) = matches.opt_str("completions") {
+        write!(out, "{}", completions(&opts, &shell)?)?;
+        return Ok(());
+    }
 
-    use std::error::Error;
-    use std::str::FromStr;
-    use std::net::{UdpSocket, SocketAddrV4, Ipv4Addr};
+    if matches.opt_present("help") {
+        writeln!(out, "{}", usage)?;
+        return Ok(());
+    }
 
-    #[cfg(test)]
-    mod test {
-        use super::{build_packet, send_packet, Mac, ParseError};
-        use std::net::{SocketAddrV4, Ipv4Addr};
+    if matches.opt_present("version") {
+        writeln!(out, "{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))?;
+        return Ok(());
+    }
 
-        #[test]
-        fn can_parse_valid_mac() {
-            assert_eq!("ff:ff:ff:ff:ff:ff".parse::<Mac>().unwrap(),
-                       Mac(255, 255, 255, 255, 255, 255));
-            assert_eq!("FF:FF:FF:FF:FF:FF".parse::<Mac>().unwrap(),
-                       Mac(255, 255, 255, 255, 255, 255));
-            assert_eq!("00:00:00:00:00:00".parse::<Mac>().unwrap(),
-                       Mac(0, 0, 0, 0, 0, 0));
+    let color = use_color(&matches)?;
+    init_logging(&matches, color);
+
+    let mut reports = Vec::new();
+    let result = wake_targets(&matches, &usage, stdin, out, env, &mut reports);
+
+    if matches.opt_present("json") {
+        match reports.len() {
+            0 => {
+                if let Err(ref e) = result {
+                    writeln!(out, "{}", json_error(&e.to_string()))?;
+                }
+            }
+            1 => writeln!(out, "{}", reports[0])?,
+            _ => writeln!(out, "[{}]", reports.join(","))?,
         }
+    }
+
+    result
+}
 
-        #[test]
-        fn return_error_for_invalid_mac() {
-            let macs = vec![":::::", "ff:ff:ff:ff:ff:fg", "ff:ff:ff:ff:ff:ff:ff"];
-            for m in macs {
-                match m.parse::<Mac>() {
-                    Err(e) => assert_eq!(e, ParseError::InvalidInput),
-                    Ok(_) => unreachable!(),
-                };
+fn options() -> Options {
+    let mut opts: Options = Options::new();
+
+    opts.optmulti("m", "mac", "MAC address in the form FF:FF:FF:FF:FF:FF (repeatable)", "")
+        .optmulti("b",
+                  "bcast",
+                  "broadcast address or hostname (repeatable, default $WOL_BROADCAST)",
+                  "")
+        .optopt("s", "subnet", "subnet in CIDR form, e.g. 192.168.1.0/24", "")
+        .optflag("", "all-interfaces", "broadcast on every non-loopback IPv4 interface")
+        .optopt("", "dev", "broadcast on a named interface, e.g. eth1", "")
+        .optopt("", "sweep", "wake every /24 broadcast inside a range, e.g. 192.168.0.0/22", "")
+        .optflag("", "force", "allow --sweep ranges larger than a /16")
+        .optopt("p", "password", "SecureOn password in the form FF:FF:FF:FF:FF:FF", "")
+        .optopt("P", "port", "destination UDP port (default $WOL_PORT or 9)", "")
+        .optopt("S", "source", "local address to send from", "")
+        .optopt("", "source-port", "local UDP port to send from (default any)", "")
+        .optopt("", "ttl", "IP TTL or hop limit, 0-255 (default 1 for broadcast)", "")
+        .optopt("f", "file", "file containing one MAC address per line", "")
+        .optopt("", "ethers", "wake a host named in the ethers file", "")
+        .optopt("", "ethers-file", "ethers file for --ethers (default /etc/ethers)", "")
+        .optopt("c", "count", "number of times to send each packet (default 1)", "")
+        .optopt("i", "interval", "time between sends or --daemon rounds, e.g. 250ms or 30s", "")
+        .optopt("", "stagger", "delay between each target's send, e.g. 50ms (default 0)", "")
+        .optopt("", "jitter", "randomize --stagger by up to this much either way", "")
+        .optopt("r", "retries", "times to retry a failed send with backoff (default 0)", "")
+        .optopt("t", "timeout", "milliseconds to wait for each send (default 5000)", "")
+        .optflag("", "strict", "refuse non-broadcast targets and multicast MAC addresses")
+        .optflag("", "both-ports", "send to both port 7 and port 9 (overrides --port)")
+        .optflag("", "show-vendor", "print the hardware vendor of each MAC before sending")
+        .optopt("", "mac-format", "lower-colon, upper-colon, lower-dash, cisco or bare", "")
+        .optopt("", "template", "success message, e.g. \"woke {mac} on {broadcast}:{port}\"", "")
+        .optflag("", "lenient", "accept MAC addresses with any separators between octets")
+        .optflag("", "stdin", "read a MAC address from standard input")
+        .optflag("", "show-packet", "print a hex dump of each packet to stderr before sending")
+        .optflag("n", "dry-run", "print the packet and destination without sending")
+        .optopt("", "pcap", "write the packets to a pcap file instead of sending them", "")
+        .optflag("", "daemon", "keep waking every --interval (default 30s) until Ctrl-C")
+        .optflag("", "wait", "after sending, wait until --check accepts TCP connections")
+        .optopt("", "check", "host:port to poll with --wait, e.g. 192.168.1.50:22", "")
+        .optopt("", "wait-timeout", "how long --wait polls for, e.g. 90s (default 5m)", "")
+        .optflag("", "self-test", "send a magic packet to a loopback listener and verify it")
+        .optflag("l", "listen", "print the MAC of every magic packet received on --port")
+        .optflag("", "json", "print results as JSON")
+        .optopt("", "color", "colorize status lines: auto, always or never (default auto)", "")
+        .optflag("q", "quiet", "suppress all output except errors")
+        .optflagmulti("v", "verbose", "log destinations and byte counts (repeat for more)")
+        .optopt("", "completions", "print a completion script for bash, zsh or fish", "")
+        .optflag("V", "version", "print the version and exit")
+        .optflag("h", "help", "display this help");
+
+    #[cfg(feature = "raw")]
+    opts.optflag("", "raw", "send a raw Ethernet frame instead of a UDP datagram")
+        .optopt("I", "interface", "network interface for --raw, e.g. eth0", "");
+
+    #[cfg(feature = "serve")]
+    opts.optopt("", "serve", "accept HTTP wake requests on an address, e.g. 0.0.0.0:8080", "");
+
+    #[cfg(feature = "config")]
+    opts.optopt("", "host", "wake a host defined in the config or hosts file", "")
+        .optflag("", "interactive", "pick a host from the config or hosts file to wake")
+        .optopt("", "hosts-file", "TOML or YAML hosts file (default wol-hosts.toml)", "")
+        .optopt("", "config", "config file (default ~/.config/wol/config.toml)", "");
+
+    opts
+}
+
+fn wake_targets<R: BufRead, W: Write>(matches: &Matches,
+                                      usage: &str,
+                                      mut stdin: R,
+                                      out: &mut W,
+                                      env: &dyn Fn(&str) -> Option<String>,
+                                      reports: &mut Vec<String>)
+                                      -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "config")]
+    let mut config = {
+        let explicit = matches.opt_str("config");
+        wol::config::load_config(explicit.as_ref().map(Path::new)).map_err(|e| match explicit {
+            Some(ref path) => format!("could not load config {}: {}", path, e),
+            None => format!("could not load config: {}", e),
+        })?
+    };
+
+    #[cfg(feature = "config")]
+    let (default_port, default_broadcast) = (config.port, config.broadcast.take());
+
+    #[cfg(not(feature = "config"))]
+    let (default_port, default_broadcast): (Option<u16>, Option<String>) = (None, None);
+
+    let port = pick_port(matches.opt_str("port"), env("WOL_PORT"), default_port)?;
+
+    if matches.opt_present("listen") {
+        return listen(port);
+    }
+
+    if matches.opt_present("self-test") {
+        return match wol::self_test() {
+            Ok(mac) => {
+                info!("self test passed: received magic packet for {}", mac);
+                Ok(())
             }
+            Err(e) => Err(format!("self test failed: {}", e).into()),
+        };
+    }
+
+    #[cfg(feature = "serve")]
+    {
+        if let Some(addr) = matches.opt_str("serve") {
+            return serve::serve(&addr);
         }
+    }
+
+    #[cfg(feature = "config")]
+    let host: Option<(wol::Mac, String, Option<u16>)> = match matches.opt_str("host") {
+        alias if alias.is_some() || matches.opt_present("interactive") => {
+            let mut hosts = config.hosts;
+            let path = matches.opt_str("hosts-file");
+
+            if path.is_some() || Path::new(DEFAULT_HOSTS_FILE).exists() {
+                let path = path.unwrap_or_else(|| DEFAULT_HOSTS_FILE.to_string());
+                hosts.extend(wol::config::load_hosts(&path)
+                    .map_err(|e| format!("could not load {}: {}", path, e))?);
+            }
 
-        #[test]
-        fn can_build_magic_packet() {
-            let mac: Mac = "ff:ff:ff:ff:ff:ff".parse().unwrap();
-            assert_eq!(build_packet(&mac).unwrap().is_empty(), false);
-            assert_eq!(build_packet(&mac).unwrap().len(), 102);
-            assert_eq!(build_packet(&mac).unwrap(), vec![255; 102]);
+            let alias = match alias {
+                Some(alias) => alias,
+                None => {
+                    match pick_host(&hosts)? {
+                        Some(alias) => alias,
+                        None => return Ok(()),
+                    }
+                }
+            };
+
+            match hosts.remove(&alias) {
+                Some(entry) => Some((entry.mac, entry.broadcast, entry.port)),
+                None => {
+                    let mut names: Vec<String> = hosts.keys().cloned().collect();
+                    names.sort();
+                    return Err(format!("unknown host {} (known hosts: {})",
+                                       alias,
+                                       names.join(", "))
+                        .into());
+                }
+            }
         }
+        _ => None,
+    };
 
-        #[test]
-        fn can_send_packet_loopback() {
-            let raddr = SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 9);
-            assert_eq!(send_packet(&vec![0xff; 102], &raddr).unwrap(), true);
+    #[cfg(not(feature = "config"))]
+    let host: Option<(wol::Mac, String, Option<u16>)> = None;
+
+    let host = match matches.opt_str("ethers") {
+        Some(_) if host.is_some() => {
+            return Err("--ethers cannot be used with --host or --interactive".into());
         }
+        Some(name) => Some(ethers_host(matches, &name)?),
+        None => host,
+    };
+
+    let port = match host {
+        Some((_, _, Some(host_port))) if !matches.opt_present("port") => host_port,
+        _ => port,
+    };
+
+    if !matches.opt_present("mac") && !matches.opt_present("file") &&
+       !matches.opt_present("stdin") && host.is_none() {
+        writeln!(out, "{}", usage)?;
+        return Ok(());
     }
 
-    #[derive(Debug)]
-    pub enum WolError {
-        InvalidBufferLength,
-        InvalidPacketSize,
+    let password: Option<wol::Password> = match matches.opt_str("password") {
+        Some(p) => Some(p.parse().map_err(|e| format!("could not parse password: {}", e))?),
+        None => None,
+    };
+
+    #[cfg(feature = "raw")]
+    {
+        if matches.opt_present("raw") {
+            let interface = matches.opt_str("interface").ok_or("--raw requires --interface")?;
+            let mut report = wol::WakeReport::default();
+            let macs: Vec<wol::Mac> =
+                collect_macs(matches, host.as_ref().map(|h| h.0), &mut stdin, &mut report)?
+                    .into_iter()
+                    .map(|(_, mac)| mac)
+                    .collect();
+            return wake_raw(&interface, &macs, password.as_ref());
+        }
     }
 
-    #[derive(Debug, PartialEq)]
-    pub enum ParseError {
-        FailedConversion,
-        InvalidInput,
-        InvalidLength,
+    let all_interfaces = matches.opt_present("all-interfaces");
+    let dev = matches.opt_str("dev");
+
+    let targets: Vec<(SocketAddr, Option<IpAddr>)> = match matches.opt_str("sweep") {
+        Some(_) if all_interfaces || dev.is_some() => {
+            return Err("--all-interfaces and --dev cannot be used with --sweep".into());
+        }
+        None if all_interfaces && dev.is_some() => {
+            return Err("--all-interfaces and --dev cannot be used together".into());
+        }
+        None if all_interfaces || dev.is_some() => {
+            if matches.opt_present("bcast") || matches.opt_present("subnet") {
+                return Err("--all-interfaces and --dev cannot be used with --bcast or --subnet"
+                    .into());
+            }
+
+            let interfaces = match dev {
+                Some(name) => {
+                    vec![wol::find_interface(&name)
+                             .map_err(|e| format!("could not use interface {}: {}", name, e))?]
+                }
+                None => {
+                    wol::local_interfaces()
+                        .map_err(|e| format!("could not list network interfaces: {}", e))?
+                }
+            };
+
+            if interfaces.is_empty() {
+                return Err("no IPv4 interfaces to broadcast on".into());
+            }
+
+            interface_targets(&interfaces, port)
+        }
+        Some(range) => {
+            if matches.opt_present("bcast") || matches.opt_present("subnet") {
+                return Err("--sweep cannot be used with --bcast or --subnet".into());
+            }
+
+            let broadcasts = wol::sweep_broadcasts(&range)
+                .map_err(|e| format!("could not parse sweep range {}: {}", range, e))?;
+
+            if broadcasts.len() > MAX_SWEEP && !matches.opt_present("force") {
+                return Err(format!("{} covers {} /24 networks, pass --force to wake them all",
+                                   range,
+                                   broadcasts.len())
+                    .into());
+            }
+
+            broadcasts.map(|b| (SocketAddr::new(IpAddr::V4(b), port), None)).collect()
+        }
+        None => {
+            let host_broadcast = host.as_ref().map(|h| h.1.clone());
+
+            let broadcasts =
+                resolve_broadcasts(matches, port, host_broadcast, default_broadcast, env)?;
+
+            if broadcasts.is_empty() {
+                writeln!(out, "{}", usage)?;
+                return Ok(());
+            }
+
+            broadcasts.into_iter().map(|b| (b, None)).collect()
+        }
+    };
+
+    for &(target, _) in &targets {
+        debug!("resolved destination {}", target);
     }
 
-    #[derive(Debug, PartialEq)]
-    pub struct Mac(u8, u8, u8, u8, u8, u8);
+    let source: Option<IpAddr> = match matches.opt_str("source") {
+        Some(s) => Some(s.parse().map_err(|e| format!("could not parse source address: {}", e))?),
+        None => None,
+    };
+
+    let source_port: u16 = match matches.opt_str("source-port") {
+        Some(p) => p.parse().map_err(|e| format!("could not parse source port: {}", e))?,
+        None => 0,
+    };
 
-    impl Mac {
-        pub fn new(a: (u8, u8, u8, u8, u8, u8)) -> Mac {
-            Mac(a.0, a.1, a.2, a.3, a.4, a.5)
+    let ttl: Option<u8> = match matches.opt_str("ttl") {
+        Some(t) => {
+            match t.parse() {
+                Ok(ttl) => Some(ttl),
+                Err(e) => return Err(format!("could not parse ttl: {} (must be 0-255)", e).into()),
+            }
         }
+        None => None,
+    };
 
-        fn as_bytes(&self) -> [u8; 6] {
-            [self.0, self.1, self.2, self.3, self.4, self.5]
+    let timeout = match matches.opt_str("timeout") {
+        Some(t) => {
+            match t.parse() {
+                Ok(0) => return Err("could not parse timeout: must be greater than zero".into()),
+                Ok(ms) => Duration::from_millis(ms),
+                Err(e) => return Err(format!("could not parse timeout: {}", e).into()),
+            }
+        }
+        None => wol::DEFAULT_TIMEOUT,
+    };
+
+    let send_opts = wol::SendOptions {
+        source,
+        source_port,
+        timeout,
+        ttl,
+        ..wol::SendOptions::default()
+    };
+
+    let count: u32 = match matches.opt_str("count") {
+        Some(c) => {
+            match c.parse() {
+                Ok(0) => return Err("could not parse count: must be greater than zero".into()),
+                Ok(n) => n,
+                Err(e) => return Err(format!("could not parse count: {}", e).into()),
+            }
         }
+        None => 1,
+    };
+
+    let mac_format: wol::MacFormat = match matches.opt_str("mac-format") {
+        Some(f) => f.parse().map_err(|e| format!("could not parse MAC format {}: {}", f, e))?,
+        None => wol::MacFormat::default(),
+    };
+
+    let template = match matches.opt_str("template") {
+        Some(t) => {
+            Some(check_template(&t).map_err(|e| format!("could not parse template: {}", e))?)
+        }
+        None => None,
+    };
+
+    let daemon = matches.opt_present("daemon");
+
+    let interval = match matches.opt_str("interval") {
+        Some(i) => parse_duration(&i).map_err(|e| format!("could not parse interval: {}", e))?,
+        None if daemon => DAEMON_INTERVAL,
+        None => REPEAT_INTERVAL,
+    };
+
+    if daemon && interval.is_zero() {
+        return Err("could not parse interval: must be greater than zero with --daemon".into());
     }
 
-    impl FromStr for Mac {
-        type Err = ParseError;
+    // With --daemon the interval spaces out rounds, and --count repeats keep the default gap.
+    let repeat_interval = if daemon { REPEAT_INTERVAL } else { interval };
+
+    let stagger = match matches.opt_str("stagger") {
+        Some(s) => parse_duration(&s).map_err(|e| format!("could not parse stagger: {}", e))?,
+        None => Duration::from_millis(0),
+    };
+
+    let jitter = match matches.opt_str("jitter") {
+        Some(j) => parse_duration(&j).map_err(|e| format!("could not parse jitter: {}", e))?,
+        None => Duration::from_millis(0),
+    };
 
-        fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let valid_mac = {
-                Regex::new("^([0-9A-Fa-f]{2}:){5}([0-9A-Fa-f]{2})$").unwrap()
+    let retries: u32 = match matches.opt_str("retries") {
+        Some(r) => r.parse().map_err(|e| format!("could not parse retries: {}", e))?,
+        None => 0,
+    };
+
+    let wait = match (matches.opt_present("wait"), matches.opt_str("check")) {
+        (true, Some(check)) => {
+            let addr = check.to_socket_addrs()
+                .map_err(|e| format!("could not resolve {}: {}", check, e))?
+                .next()
+                .ok_or_else(|| format!("could not resolve {}", check))?;
+            let timeout = match matches.opt_str("wait-timeout") {
+                Some(t) => {
+                    parse_duration(&t).map_err(|e| format!("could not parse wait timeout: {}", e))?
+                }
+                None => WAIT_TIMEOUT,
             };
+            Some((addr, timeout))
+        }
+        (true, None) => return Err("--wait requires --check".into()),
+        (false, _) => None,
+    };
 
-            if valid_mac.is_match(s) {
-                match s.split(':')
-                    .map(|e| u8::from_str_radix(e, 16))
-                    .collect::<Result<Vec<_>, _>>() {
-                    Ok(r) => {
-                        if r.len() == 6 {
-                            Ok(Mac::new((r[0], r[1], r[2], r[3], r[4], r[5])))
-                        } else {
-                            Err(ParseError::InvalidLength)
-                        }
-                    }
-                    Err(_) => Err(ParseError::FailedConversion),
+    let mut report = wol::WakeReport::default();
+
+    let round = Round {
+        macs: collect_macs(matches, host.map(|h| h.0), &mut stdin, &mut report)?,
+        targets: dedup_by_key(targets, |&(target, _)| target).into_iter()
+            .map(|(target, source)| {
+                (target,
+                 wol::SendOptions { source: source.or(send_opts.source), ..send_opts.clone() })
+            })
+            .collect(),
+        password,
+        mac_format,
+        template,
+        count,
+        interval: repeat_interval,
+        stagger,
+        jitter,
+        retries,
+    };
+
+    if let Some(path) = matches.opt_str("pcap") {
+        return write_pcap(&path, &round);
+    }
+
+    if daemon {
+        let running = Arc::new(AtomicBool::new(true));
+        let handler_flag = running.clone();
+        ctrlc::set_handler(move || handler_flag.store(false, Ordering::SeqCst))
+            .map_err(|e| format!("could not install Ctrl-C handler: {}", e))?;
+
+        info!("waking every {:?}, press Ctrl-C to stop", interval);
+
+        let (rounds, sent) = run_daemon(&running, interval, || {
+            let mut report = wol::WakeReport::default();
+            reports.clear();
+            wake_round(matches, &round, reports, &mut report)
+        });
+
+        info!("sent {} magic packet(s) over {} round(s)", sent, rounds);
+        return Ok(());
+    }
+
+    wake_round(matches, &round, reports, &mut report);
+
+    if matches.opt_present("file") {
+        info!("{} succeeded, {} failed", report.succeeded, report.failed);
+        for &(line, ref mac, ref e) in &report.failures {
+            match line {
+                0 => info!("  {}: {}", mac, e),
+                _ => info!("  line {} ({}): {}", line, mac, e),
+            }
+        }
+    }
+
+    if report.succeeded == 0 {
+        return Err("no packets were sent".into());
+    }
+
+    if let Some((addr, timeout)) = wait {
+        info!("waiting up to {:?} for {} to come online", timeout, addr);
+        let elapsed = wol::wait_for_host(&addr, timeout, WAIT_INTERVAL)
+            .map_err(|e| format!("{} did not come online: {}", addr, e))?;
+        info!(target: SUCCESS, "{} came online after {:.1}s", addr, elapsed.as_secs_f64());
+    }
+
+    Ok(())
+}
+
+struct Round {
+    macs: Vec<(usize, wol::Mac)>,
+    targets: Vec<(SocketAddr, wol::SendOptions)>,
+    password: Option<wol::Password>,
+    mac_format: wol::MacFormat,
+    template: Option<String>,
+    count: u32,
+    interval: Duration,
+    stagger: Duration,
+    jitter: Duration,
+    retries: u32,
+}
+
+fn run_daemon<F>(running: &AtomicBool, interval: Duration, mut round: F) -> (usize, usize)
+    where F: FnMut() -> usize
+{
+    let (mut rounds, mut sent) = (0, 0);
+
+    while running.load(Ordering::SeqCst) {
+        sent += round();
+        rounds += 1;
+
+        // Sleep in short ticks so Ctrl-C is noticed promptly, but never in the
+        // middle of a send: sockets are only ever dropped after send_to returns.
+        let deadline = Instant::now() + interval;
+        while running.load(Ordering::SeqCst) {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            thread::sleep(DAEMON_TICK.min(deadline - now));
+        }
+    }
+
+    (rounds, sent)
+}
+
+fn wake_round(matches: &Matches,
+              round: &Round,
+              reports: &mut Vec<String>,
+              report: &mut wol::WakeReport)
+              -> usize {
+    let json = matches.opt_present("json");
+    let mut sends = 0;
+    let mut sent = 0;
+
+    for &(line, ref mac) in &round.macs {
+        let shown = mac.format(round.mac_format);
+
+        if matches.opt_present("show-vendor") {
+            info!("{}: {}", shown, mac.vendor().unwrap_or("unknown vendor"));
+        }
+
+        if matches.opt_present("strict") && !mac.is_unicast() && !mac.is_broadcast() {
+            let message = format!("{} is a multicast MAC address", shown);
+            error!("could not build packet for {}: {}", shown, message);
+            if json {
+                let target = &round.targets[0].0;
+                reports.push(json_report(&shown, target, "error", None, Some(&message)));
+            }
+            report.fail(line, &shown, &message);
+            continue;
+        }
+
+        let built = match round.password {
+            Some(ref p) => wol::build_packet_with_password(mac, p),
+            None => wol::build_packet(mac),
+        };
+
+        let magic_packet = match built {
+            Ok(packet) => packet,
+            Err(e) => {
+                let message = e.to_string();
+                error!("could not build packet for {}: {}", shown, message);
+                if json {
+                    let target = &round.targets[0].0;
+                    reports.push(json_report(&shown, target, "error", None, Some(&message)));
                 }
+                report.fail(line, &shown, &message);
+                continue;
+            }
+        };
+
+        debug!("built {} byte packet for {}", magic_packet.len(), shown);
+
+        if matches.opt_present("show-packet") && !matches.opt_present("dry-run") {
+            eprint!("{}", wol::hex_dump(&magic_packet));
+        }
+
+        if matches.opt_present("dry-run") {
+            for (raddr, _) in &round.targets {
+                info!("would send {} bytes for {} to {} ({} time(s))",
+                      magic_packet.len(),
+                      shown,
+                      raddr,
+                      round.count);
+                if json {
+                    reports.push(json_report(&shown, raddr, "dry-run", None, None));
+                }
+            }
+            info!("{}", wol::hex_dump(&magic_packet).trim_end());
+            report.succeeded += 1;
+            continue;
+        }
+
+        let mut outcomes = Vec::new();
+
+        for &(raddr, ref send_opts) in &round.targets {
+            if sends > 0 && !(round.stagger + round.jitter).is_zero() {
+                thread::sleep(jittered(round.stagger, round.jitter, random_u64()));
+            }
+            sends += 1;
+
+            if matches.opt_present("both-ports") {
+                outcomes.extend(send_to_both_ports(&magic_packet,
+                                                   raddr,
+                                                   send_opts,
+                                                   round.count,
+                                                   round.interval,
+                                                   round.retries));
             } else {
-                Err(ParseError::InvalidInput)
+                let result = wol::send_packet_repeated_retry(&magic_packet,
+                                                             &raddr,
+                                                             send_opts,
+                                                             round.count,
+                                                             round.interval,
+                                                             round.retries.saturating_add(1),
+                                                             RETRY_DELAY);
+                outcomes.push((raddr, result.map_err(|e| e.to_string())));
             }
         }
+
+        let mut failure = None;
+
+        for (target, outcome) in outcomes {
+            match (&outcome, &round.template) {
+                (Err(e), _) => {
+                    error!("could not send request for {} to {}: {}", shown, target, e);
+                    failure.get_or_insert_with(|| format!("{}: {}", target, e));
+                }
+                (&Ok(n), Some(template)) if !json => {
+                    info!(target: SUCCESS, "{}", render_template(template, &shown, &target, n));
+                }
+                _ => {}
+            }
+
+            if outcome.is_ok() {
+                sent += round.count as usize;
+            }
+
+            if json {
+                reports.push(match outcome {
+                    Ok(n) => json_report(&shown, &target, "ok", Some(n), None),
+                    Err(e) => json_report(&shown, &target, "error", None, Some(&e)),
+                });
+            }
+        }
+
+        match failure {
+            Some(e) => report.fail(line, &shown, &e),
+            None => {
+                if !json && round.template.is_none() {
+                    info!(target: SUCCESS, "packet sent Ok");
+                }
+                report.succeeded += 1;
+            }
+        }
+    }
+
+    sent
+}
+
+fn jittered(stagger: Duration, jitter: Duration, random: u64) -> Duration {
+    let span = jitter.as_nanos() * 2 + 1;
+    let offset = Duration::from_nanos((u128::from(random) % span) as u64);
+    stagger.saturating_add(offset).saturating_sub(jitter)
+}
+
+#[cfg(feature = "rand")]
+fn random_u64() -> u64 {
+    rand::random()
+}
+
+// Without the rand feature the per-process RandomState keys are the only entropy to hand.
+// That is enough to spread sends out, but it is not a random number generator.
+#[cfg(not(feature = "rand"))]
+fn random_u64() -> u64 {
+    RandomState::new().hash_one(SystemTime::now())
+}
+
+fn check_template(template: &str) -> Result<String, String> {
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or("unclosed { in template")? + start;
+
+        match &rest[start + 1..end] {
+            "mac" | "broadcast" | "port" | "bytes" => rest = &rest[end + 1..],
+            name => {
+                return Err(format!("unknown placeholder {{{}}}, expected {{mac}}, {{broadcast}}, \
+                                    {{port}} or {{bytes}}",
+                                   name))
+            }
+        }
+    }
+
+    Ok(template.to_string())
+}
+
+fn render_template(template: &str, mac: &str, target: &SocketAddr, bytes: usize) -> String {
+    template.replace("{mac}", mac)
+        .replace("{broadcast}", &target.ip().to_string())
+        .replace("{port}", &target.port().to_string())
+        .replace("{bytes}", &bytes.to_string())
+}
+
+#[cfg(feature = "tui")]
+fn pick_host(hosts: &wol::config::Hosts) -> Result<Option<String>, Box<dyn Error>> {
+    let mut names: Vec<String> = hosts.keys().cloned().collect();
+    names.sort();
+
+    if names.is_empty() {
+        return Err("no hosts in the config or hosts file to pick from".into());
     }
 
-    pub fn build_packet(mac: &Mac) -> Result<Vec<u8>, WolError> {
-        let mut packet = vec![0xff; 6];
-        let payload = mac.as_bytes();
+    Ok(tui::pick(&names)?.map(|i| names[i].clone()))
+}
+
+#[cfg(all(feature = "config", not(feature = "tui")))]
+fn pick_host(_: &wol::config::Hosts) -> Result<Option<String>, Box<dyn Error>> {
+    Err("--interactive needs wol built with --features tui".into())
+}
+
+fn write_pcap(path: &str, round: &Round) -> Result<(), Box<dyn Error>> {
+    let mut frames = Vec::new();
 
-        match payload.len() {
-            6 => {
-                for _ in 0..16 {
-                    packet.extend_from_slice(&payload);
+    for (_, mac) in &round.macs {
+        let packet = match round.password {
+            Some(ref p) => wol::build_packet_with_password(mac, p)?,
+            None => wol::build_packet(mac)?,
+        };
+
+        for (target, opts) in &round.targets {
+            let dst = match *target {
+                SocketAddr::V4(dst) => dst,
+                SocketAddr::V6(_) => {
+                    return Err(format!("--pcap does not support IPv6 target {}", target).into())
                 }
+            };
+            let src = match opts.source {
+                Some(IpAddr::V4(ip)) => ip,
+                _ => Ipv4Addr::UNSPECIFIED,
+            };
+            let src = SocketAddrV4::new(src, opts.source_port);
+            frames.push(wol::build_udp_frame(src, dst, &packet));
+        }
+    }
+
+    if frames.is_empty() {
+        return Err("no packets to write".into());
+    }
+
+    let file = File::create(path).map_err(|e| format!("could not create {}: {}", path, e))?;
+    wol::write_pcap(BufWriter::new(file), &frames)
+        .map_err(|e| format!("could not write {}: {}", path, e))?;
+
+    info!(target: SUCCESS, "wrote {} packet(s) to {}", frames.len(), path);
+    Ok(())
+}
+
+fn interface_targets(interfaces: &[wol::LocalInterface],
+                     port: u16)
+                     -> Vec<(SocketAddr, Option<IpAddr>)> {
+    interfaces.iter()
+        .map(|i| {
+            debug!("{} has address {}/{}", i.name, i.addr, i.netmask);
+            (SocketAddr::new(IpAddr::V4(i.broadcast()), port), Some(IpAddr::V4(i.addr)))
+        })
+        .collect()
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (digits, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "ms"),
+    };
+
+    let n: u64 = digits.parse().map_err(|e| format!("{}", e))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(n)),
+        "s" => Ok(Duration::from_secs(n)),
+        "m" => {
+            n.checked_mul(60)
+                .map(Duration::from_secs)
+                .ok_or_else(|| format!("{}m is too long", n))
+        }
+        _ => Err(format!("unknown unit {:?}, expected ms, s or m", unit)),
+    }
+}
+
+fn collect_macs<R: BufRead>(matches: &Matches,
+                            host_mac: Option<wol::Mac>,
+                            stdin: &mut R,
+                            report: &mut wol::WakeReport)
+                            -> Result<Vec<(usize, wol::Mac)>, Box<dyn Error>> {
+    let mut macs: Vec<(usize, wol::Mac)> = Vec::new();
+
+    if let Some(mac) = host_mac {
+        macs.push((0, mac));
+    }
+
+    let lenient = matches.opt_present("lenient");
+    let parse = |s: &str| if lenient { wol::Mac::parse_lenient(s) } else { s.parse() };
+
+    for m in matches.opt_strs("mac") {
+        match parse(&m) {
+            Ok(mac) => macs.push((0, mac)),
+            Err(e) => {
+                error!("could not parse mac {}: {}", m, e);
+                report.fail(0, &m, &e.to_string());
             }
-            _ => return Err(WolError::InvalidBufferLength),
         }
+    }
 
-        match packet.len() {
-            102 => return Ok(packet),
-            _ => return Err(WolError::InvalidPacketSize),
+    if matches.opt_present("stdin") {
+        let mut line = String::new();
+        stdin.read_line(&mut line).map_err(|e| format!("could not read stdin: {}", e))?;
+
+        if line.trim().is_empty() {
+            return Err("no MAC address on stdin".into());
         }
+
+        let mac = parse(&line).map_err(|e| format!("could not parse mac {}: {}", line.trim(), e))?;
+        macs.push((0, mac));
     }
 
-    pub fn send_packet(p: &[u8], r: &SocketAddrV4) -> Result<bool, Box<Error>> {
-        let laddr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0);
-        let socket = try!(UdpSocket::bind(laddr));
+    if let Some(path) = matches.opt_str("file") {
+        let file = File::open(&path).map_err(|e| format!("could not open {}: {}", path, e))?;
 
-        try!(socket.send_to(&p[0..102], r));
+        let entries = wol::parse_mac_list(BufReader::new(file))
+            .map_err(|e| format!("could not read {}: {}", path, e))?;
+
+        for (line, content, parsed) in entries {
+            let parsed = match parsed {
+                Err(_) if lenient => wol::Mac::parse_lenient(&content),
+                parsed => parsed,
+            };
 
-        Ok(true)
+            match parsed {
+                Ok(mac) => macs.push((line, mac)),
+                Err(e) => {
+                    error!("could not parse mac on line {} ({}): {}", line, content, e);
+                    report.fail(line, &content, &e.to_string());
+                }
+            }
+        }
     }
+
+    Ok(dedup_by_key(macs, |&(_, mac)| mac))
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut opts: Options = Options::new();
+fn dedup_by_key<T, K, F>(items: Vec<T>, key: F) -> Vec<T>
+    where K: Eq + Hash,
+          F: Fn(&T) -> K
+{
+    let mut seen = HashSet::new();
+    items.into_iter().filter(|item| seen.insert(key(item))).collect()
+}
 
-    opts.optopt("m", "mac", "MAC address in the form FF:FF:FF:FF:FF:FF", "")
-        .optopt("b", "bcast", "broadcast address", "")
-        .optflag("h", "help", "display this help");
+fn ethers_host(matches: &Matches,
+               name: &str)
+               -> Result<(wol::Mac, String, Option<u16>), Box<dyn Error>> {
+    let path = matches.opt_str("ethers-file").unwrap_or_else(|| DEFAULT_ETHERS_FILE.to_string());
+    let file = File::open(&path).map_err(|e| format!("could not open {}: {}", path, e))?;
 
-    let name = args[0].clone();
+    let hosts = wol::parse_ethers(BufReader::new(file))
+        .map_err(|e| format!("could not read {}: {}", path, e))?;
 
-    let usage = format!("Usage: {}", opts.usage(&(name + " [options]")));
+    let mac = match hosts.into_iter().find(|h| h.1 == name) {
+        Some((mac, _)) => mac,
+        None => return Err(format!("{} is not listed in {}", name, path).into()),
+    };
 
-    let exit = |msg: &str, code: i32| -> ! {
-        println!("{}", msg);
-        process::exit(code);
+    let interfaces = wol::local_interfaces().unwrap_or_default();
+    let broadcast = match wol::broadcast_for_host(name, &interfaces) {
+        Some(b) => b,
+        None => {
+            info!("no local network found for {}, using 255.255.255.255", name);
+            Ipv4Addr::new(255, 255, 255, 255)
+        }
     };
 
-    let matches = opts.parse(&args[1..])
-        .unwrap_or_else(|e| exit(&format!("could not parse args: {:?}", e), 1));
+    Ok((mac, broadcast.to_string(), None))
+}
 
-    if matches.opt_present("help") {
-        exit(&usage, 0);
+// The command line wins over the environment, which wins over the config file.
+fn pick_port(cli: Option<String>, env: Option<String>, config: Option<u16>) -> Result<u16, String> {
+    match (cli, env) {
+        (Some(p), _) => p.parse().map_err(|e| format!("could not parse port: {}", e)),
+        (None, Some(p)) => p.parse().map_err(|e| format!("could not parse WOL_PORT: {}", e)),
+        (None, None) => Ok(config.unwrap_or(9)),
+    }
+}
+
+fn pick_broadcasts(cli: Vec<String>,
+                   host: Option<String>,
+                   env: Option<String>,
+                   config: Option<String>)
+                   -> Vec<String> {
+    if !cli.is_empty() {
+        return cli;
     }
 
-    let mac: wol::Mac = match matches.opt_str("mac") {
-        Some(m) => {
-            m.parse()
-                .unwrap_or_else(|e| exit(&format!("could not parse mac: {:?}", e), 1))
+    host.or(env).or(config).into_iter().collect()
+}
+
+fn resolve_broadcasts(matches: &Matches,
+                      port: u16,
+                      host_broadcast: Option<String>,
+                      default_broadcast: Option<String>,
+                      env: &dyn Fn(&str) -> Option<String>)
+                      -> Result<Vec<SocketAddr>, Box<dyn Error>> {
+    let given = matches.opt_strs("bcast");
+
+    let names = match (given.is_empty(), matches.opt_str("subnet")) {
+        (false, Some(_)) => return Err("--bcast and --subnet cannot be used together".into()),
+        (true, Some(s)) => {
+            return wol::broadcast_for_cidr(&s)
+                .map(|b| vec![SocketAddr::new(IpAddr::V4(b), port)])
+                .map_err(|e| format!("could not parse subnet {}: {}", s, e).into())
+        }
+        (_, None) => {
+            pick_broadcasts(given, host_broadcast, env("WOL_BROADCAST"), default_broadcast)
         }
-        None => exit(&usage, 0),
     };
 
-    let bcast: Ipv4Addr = match matches.opt_str("bcast") {
-        Some(b) => {
-            b.parse()
-                .unwrap_or_else(|e| exit(&format!("could not parse ip: {:?}", e), 1))
+    let mut broadcasts = Vec::new();
+
+    for b in &names {
+        match check_broadcast(matches, b, port) {
+            Ok(bcast) => broadcasts.push(bcast),
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    if broadcasts.is_empty() && !names.is_empty() {
+        return Err("none of the broadcast addresses could be used".into());
+    }
+
+    Ok(broadcasts)
+}
+
+fn check_broadcast(matches: &Matches, b: &str, port: u16) -> Result<SocketAddr, String> {
+    let bcast = wol::resolve_destination(b, port)
+        .map_err(|e| format!("could not resolve {}: {}", b, e))?;
+
+    if let SocketAddr::V4(addr) = bcast {
+        let ip = *addr.ip();
+        if !wol::looks_like_broadcast(&ip) {
+            if matches.opt_present("strict") {
+                return Err(format!("{} is not a broadcast or multicast address", ip));
+            }
+            warn!("{} does not look like a broadcast or multicast address", ip);
+        }
+    }
+
+    Ok(bcast)
+}
+
+#[cfg(feature = "raw")]
+fn wake_raw(interface: &str,
+            macs: &[wol::Mac],
+            password: Option<&wol::Password>)
+            -> Result<(), Box<dyn Error>> {
+    let mut sent = 0;
+
+    for mac in macs {
+        let built = match password {
+            Some(p) => wol::build_packet_with_password(mac, p),
+            None => wol::build_packet(mac),
+        };
+
+        let result = built.map_err(|e| e.into())
+            .and_then(|packet| wol::send_raw_packet(interface, mac, &packet));
+
+        match result {
+            Ok(n) => {
+                debug!("sent {} byte frame for {} on {}", n, mac, interface);
+                info!("packet sent Ok");
+                sent += 1;
+            }
+            Err(e) => error!("could not send request for {} on {}: {}", mac, interface, e),
+        }
+    }
+
+    if sent == 0 {
+        return Err("no packets were sent".into());
+    }
+
+    Ok(())
+}
+
+fn completions(opts: &Options, shell: &str) -> Result<String, Box<dyn Error>> {
+    let name = env!("CARGO_PKG_NAME");
+    let mut flags: Vec<(Option<&str>, &str)> = Vec::new();
+
+    let usage = opts.usage("");
+    for line in usage.lines() {
+        let spec = line.trim_start();
+        if line.len() - spec.len() > 8 || !spec.starts_with('-') {
+            continue;
+        }
+
+        let spec = spec.split("  ").next().unwrap_or("");
+        match spec.split(", ").collect::<Vec<_>>()[..] {
+            [short, long] => flags.push((Some(&short[1..]), &long[2..])),
+            [long] => flags.push((None, &long[2..])),
+            _ => {}
         }
-        None => exit(&usage, 0),
+    }
+
+    let mut words = Vec::new();
+    for &(short, long) in &flags {
+        if let Some(s) = short {
+            words.push(format!("-{}", s));
+        }
+        words.push(format!("--{}", long));
+    }
+
+    match shell {
+        "bash" => {
+            Ok(format!("_{0}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+                        COMPREPLY=($(compgen -W \"{1}\" -- \"$cur\"))\n}}\n\
+                        complete -F _{0} {0}\n",
+                       name,
+                       words.join(" ")))
+        }
+        "zsh" => Ok(format!("#compdef {}\ncompadd -- {}\n", name, words.join(" "))),
+        "fish" => {
+            Ok(flags.iter()
+                .map(|&(short, long)| match short {
+                    Some(s) => format!("complete -c {} -s {} -l {}\n", name, s, long),
+                    None => format!("complete -c {} -l {}\n", name, long),
+                })
+                .collect())
+        }
+        _ => Err(format!("unknown shell {}, expected bash, zsh or fish", shell).into()),
+    }
+}
+
+fn use_color(matches: &Matches) -> Result<bool, Box<dyn Error>> {
+    if matches.opt_present("json") {
+        return Ok(false);
+    }
+
+    match matches.opt_str("color").as_deref() {
+        None | Some("auto") => Ok(io::stderr().is_terminal()),
+        Some("always") => Ok(true),
+        Some("never") => Ok(false),
+        Some(c) => Err(format!("unknown color mode {}, expected auto, always or never", c).into()),
+    }
+}
+
+fn write_record<W: Write>(w: W,
+                          level: Level,
+                          target: &str,
+                          message: &str,
+                          color: bool)
+                          -> io::Result<()> {
+    let fg = match level {
+        Level::Error => Some(Color::Red),
+        Level::Warn => Some(Color::Yellow),
+        Level::Info if target == SUCCESS => Some(Color::Green),
+        _ => None,
     };
 
-    let magic_packet = wol::build_packet(&mac)
-        .unwrap_or_else(|e| exit(&format!("could not build packet: {:?}", e), 1));
+    let mut w = Ansi::new(w);
 
-    let raddr = SocketAddrV4::new(bcast, 9);
+    match fg {
+        Some(fg) if color => {
+            w.set_color(ColorSpec::new().set_fg(Some(fg)))?;
+            write!(w, "{}", message)?;
+            w.reset()?;
+            writeln!(w)
+        }
+        _ => writeln!(w, "{}", message),
+    }
+}
 
-    match wol::send_packet(&magic_packet, &raddr) {
-        Ok(_) => println!("packet sent Ok"),
-        Err(e) => exit(&format!("could not send request: {:?}", e), 1),
+fn init_logging(matches: &Matches, color: bool) {
+    let level = if matches.opt_present("quiet") {
+        LevelFilter::Error
+    } else {
+        match matches.opt_count("verbose") {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
     };
+
+    let style = if color {
+        env_logger::WriteStyle::Always
+    } else {
+        env_logger::WriteStyle::Never
+    };
+
+    let _ = env_logger::Builder::new()
+        .filter_level(level)
+        .write_style(style)
+        .format(move |buf, record| {
+            write_record(buf,
+                         record.level(),
+                         record.target(),
+                         &record.args().to_string(),
+                         color)
+        })
+        .try_init();
+}
+
+fn send_to_both_ports(packet: &[u8],
+                      target: SocketAddr,
+                      opts: &wol::SendOptions,
+                      count: u32,
+                      interval: Duration,
+                      retries: u32)
+                      -> Vec<(SocketAddr, Result<usize, String>)> {
+    let ports = [7, 9];
+    let mut outcomes: Vec<(SocketAddr, Result<usize, String>)> = ports
+        .iter()
+        .map(|&port| {
+            let mut target = target;
+            target.set_port(port);
+            (target, Ok(0))
+        })
+        .collect();
+
+    for i in 0..count {
+        if i > 0 {
+            thread::sleep(interval);
+        }
+
+        let results = match wol::send_packet_ports_retry(packet,
+                                                         &target,
+                                                         &ports,
+                                                         opts,
+                                                         retries.saturating_add(1),
+                                                         RETRY_DELAY) {
+            Ok(results) => results.into_iter().map(|r| r.map_err(|e| e.to_string())).collect(),
+            Err(e) => vec![Err(e.to_string()); outcomes.len()],
+        };
+
+        for (&mut (target, ref mut total), result) in outcomes.iter_mut().zip(results) {
+            match result {
+                Ok(n) => {
+                    debug!("sent {} bytes to {}", n, target);
+                    if let Ok(ref mut t) = *total {
+                        *t += n;
+                    }
+                }
+                Err(e) => {
+                    if total.is_ok() {
+                        *total = Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    outcomes
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn json_report(mac: &str,
+               target: &SocketAddr,
+               status: &str,
+               bytes_sent: Option<usize>,
+               message: Option<&str>)
+               -> String {
+    let mut fields = vec![format!("\"mac\":{}", json_string(mac)),
+                          format!("\"target\":\"{}\"", target)];
+
+    if let Some(n) = bytes_sent {
+        fields.push(format!("\"bytes_sent\":{}", n));
+    }
+
+    fields.push(format!("\"status\":{}", json_string(status)));
+
+    if let Some(m) = message {
+        fields.push(format!("\"message\":{}", json_string(m)));
+    }
+
+    format!("{{{}}}", fields.join(","))
+}
+
+fn json_error(message: &str) -> String {
+    format!("{{\"status\":\"error\",\"message\":{}}}", json_string(message))
+}
+
+fn listen(port: u16) -> Result<(), Box<dyn Error>> {
+    let socket = UdpSocket::bind(("0.0.0.0", port))
+        .map_err(|e| format!("could not listen on port {}: {}", port, e))?;
+    let mut buf = [0; 1024];
+
+    loop {
+        let (len, src) = socket.recv_from(&mut buf)
+            .map_err(|e| format!("could not receive packet: {}", e))?;
+
+        if let Some(mac) = wol::parse_packet(&buf[..len]) {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            info!("[{}.{:03}] magic packet for {} from {}",
+                  now.as_secs(),
+                  now.subsec_millis(),
+                  mac,
+                  src);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_template, interface_targets, jittered, options, parse_duration,
+                pick_broadcasts, pick_port, render_template, run, run_daemon, run_with_env,
+                use_color, wake_round, write_record, Round, SUCCESS};
+    use log::Level;
+    use std::env;
+    use std::fs;
+    use std::io::{self, Cursor};
+    use std::net::{IpAddr, Ipv4Addr, TcpListener, UdpSocket};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn args(a: &[&str]) -> Vec<String> {
+        let mut v = vec!["wol".to_string()];
+        v.extend(a.iter().map(|s| s.to_string()));
+        v
+    }
+
+    #[test]
+    fn help_and_usage_succeed() {
+        let mut out = Vec::new();
+        assert!(run(&args(&["--help"]), io::empty(), &mut out).is_ok());
+        let help = String::from_utf8(out).unwrap();
+        assert!(help.starts_with("Usage: wol [options]"));
+        assert!(help.contains("--bcast"));
+
+        let mut out = Vec::new();
+        assert!(run(&args(&["--version", "-m", "zz"]), io::empty(), &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   format!("wol {}\n", env!("CARGO_PKG_VERSION")));
+
+        let mut out = Vec::new();
+        assert!(run(&args(&[]), io::empty(), &mut out).is_ok());
+        assert!(String::from_utf8(out).unwrap().starts_with("Usage: "));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn shows_usage_without_a_broadcast_address() {
+        let path = env::temp_dir().join(format!("wol-empty-config-{}.toml", std::process::id()));
+        fs::write(&path, "").unwrap();
+
+        let mut out = Vec::new();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "--config", path.to_str().unwrap()]);
+        let result = run_with_env(&a, io::empty(), &mut out, &|_| None);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+        assert!(String::from_utf8(out).unwrap().starts_with("Usage: "));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn host_without_port_uses_config_port() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let config = env::temp_dir().join(format!("wol-port-config-{}.toml", std::process::id()));
+        let hosts = env::temp_dir().join(format!("wol-port-hosts-{}.toml", std::process::id()));
+        fs::write(&config, format!("port = {}\n", port)).unwrap();
+        fs::write(&hosts, "[nas]\nmac = \"00:1a:2b:3c:4d:5e\"\nbroadcast = \"127.0.0.1\"\n")
+            .unwrap();
+
+        let a = args(&["--host", "nas", "--config", config.to_str().unwrap(), "--hosts-file",
+                       hosts.to_str().unwrap()]);
+        let result = run_with_env(&a, io::empty(), &mut io::sink(), &|_| None);
+        fs::remove_file(&config).unwrap();
+        fs::remove_file(&hosts).unwrap();
+        assert!(result.is_ok());
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
+
+    #[test]
+    fn can_generate_completions() {
+        let mut out = Vec::new();
+        assert!(run(&args(&["--completions", "bash"]), io::empty(), &mut out).is_ok());
+        let script = String::from_utf8(out).unwrap();
+        assert!(script.contains("complete -F _wol wol"));
+        assert!(script.contains("\"-m --mac -b --bcast "));
+        assert!(script.contains(" --sweep "));
+        assert!(script.contains(" --completions "));
+
+        let mut out = Vec::new();
+        assert!(run(&args(&["-q", "--completions", "zsh"]), io::empty(), &mut out).is_ok());
+        assert!(String::from_utf8(out).unwrap().starts_with("#compdef wol\n"));
+
+        let mut out = Vec::new();
+        assert!(run(&args(&["--completions", "fish"]), io::empty(), &mut out).is_ok());
+        assert!(String::from_utf8(out).unwrap().contains("complete -c wol -s b -l bcast\n"));
+
+        assert!(run(&args(&["--completions", "tcsh"]), io::empty(), &mut io::sink()).is_err());
+        assert!(run(&args(&["--completions"]), io::empty(), &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn return_error_for_invalid_arguments() {
+        let a = args(&["-m", "ff:ff:ff:ff:ff:ff", "-b", "127.0.0.1", "-P", "x"]);
+        let err = run(&a, io::empty(), &mut io::sink());
+        assert_eq!(err.unwrap_err().to_string(),
+                   "could not parse port: invalid digit found in string");
+
+        let err = run(&args(&["-m", "zz", "-b", "127.0.0.1"]), io::empty(), &mut io::sink());
+        assert_eq!(err.unwrap_err().to_string(), "no packets were sent");
+
+        assert!(run(&args(&["--bogus"]), io::empty(), &mut io::sink()).is_err());
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "--mac-format", "Cisco"]);
+        assert_eq!(run(&a, io::empty(), &mut io::sink()).unwrap_err().to_string(),
+                   "could not parse MAC format Cisco: MAC format must be lower-colon, upper-colon, \
+                    lower-dash, cisco or bare");
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "--ttl", "256"]);
+        assert_eq!(run(&a, io::empty(), &mut io::sink()).unwrap_err().to_string(),
+                   "could not parse ttl: number too large to fit in target type (must be 0-255)");
+
+        for extra in &[&[][..], &["--both-ports"][..]] {
+            let mut a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-c", "0"]);
+            a.extend(extra.iter().map(|s| s.to_string()));
+            assert_eq!(run(&a, io::empty(), &mut io::sink()).unwrap_err().to_string(),
+                       "could not parse count: must be greater than zero");
+        }
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "--daemon", "-i", "0"]);
+        assert_eq!(run(&a, io::empty(), &mut io::sink()).unwrap_err().to_string(),
+                   "could not parse interval: must be greater than zero with --daemon");
+    }
+
+    #[test]
+    fn dry_run_does_not_send() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_nonblocking(true).unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port, "-n"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let mut buf = [0; 256];
+        assert!(receiver.recv_from(&mut buf).is_err());
+    }
+
+    #[test]
+    fn sends_once_for_duplicate_macs() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let path = env::temp_dir().join(format!("wol-dedup-test-{}", std::process::id()));
+        fs::write(&path, "00:1a:2b:3c:4d:5e\n00:1a:2b:3c:4d:5f\n00:1a:2b:3c:4d:5e\n").unwrap();
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5f", "-f", path.to_str().unwrap(), "-b", "127.0.0.1",
+                       "-b", "127.0.0.1", "-P", &port]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+        fs::remove_file(&path).unwrap();
+
+        let mut buf = [0; 256];
+        let mut received = Vec::new();
+        receiver.set_nonblocking(true).unwrap();
+        while let Ok((len, _)) = receiver.recv_from(&mut buf) {
+            received.push(wol::parse_packet(&buf[..len]).unwrap());
+        }
+        assert_eq!(received,
+                   vec!["00:1a:2b:3c:4d:5f".parse().unwrap(),
+                        "00:1a:2b:3c:4d:5e".parse().unwrap()]);
+    }
+
+    #[test]
+    fn can_read_mac_from_stdin() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["--stdin", "-b", "127.0.0.1", "-P", &port]);
+        assert!(run(&a, Cursor::new("00:1a:2b:3c:4d:5e\n"), &mut io::sink()).is_ok());
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+
+        let err = run(&a, io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.to_string(), "no MAC address on stdin");
+        assert!(run(&a, Cursor::new("zz\n"), &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn can_print_json_result() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port, "--json"]);
+        let mut out = Vec::new();
+        assert!(run(&a, io::empty(), &mut out).is_ok());
+
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["mac"], "00:1a:2b:3c:4d:5e");
+        assert_eq!(json["target"], format!("127.0.0.1:{}", port));
+        assert_eq!(json["bytes_sent"], 102);
+        assert_eq!(json["status"], "ok");
+
+        let mut out = Vec::new();
+        assert!(run(&args(&["-m", "zz", "-b", "127.0.0.1", "--json"]), io::empty(), &mut out)
+            .is_err());
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["message"], "no packets were sent");
+
+        let mut out = Vec::new();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-n", "--json",
+                       "--mac-format", "cisco"]);
+        assert!(run(&a, io::empty(), &mut out).is_ok());
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["mac"], "001a.2b3c.4d5e");
+    }
+
+    #[test]
+    fn broadcast_and_port_fall_back_to_environment() {
+        let s = |s: &str| Some(s.to_string());
+
+        assert_eq!(pick_port(s("7"), s("1"), Some(2)), Ok(7));
+        assert_eq!(pick_port(None, s("1"), Some(2)), Ok(1));
+        assert_eq!(pick_port(None, None, Some(2)), Ok(2));
+        assert_eq!(pick_port(None, None, None), Ok(9));
+        assert_eq!(pick_port(None, s("x"), None),
+                   Err("could not parse WOL_PORT: invalid digit found in string".to_string()));
+
+        let cli = vec!["127.0.0.1".to_string()];
+        assert_eq!(pick_broadcasts(cli.clone(), s("host"), s("env"), s("config")), cli);
+        assert_eq!(pick_broadcasts(vec![], s("host"), s("env"), s("config")), vec!["host"]);
+        assert_eq!(pick_broadcasts(vec![], None, s("env"), s("config")), vec!["env"]);
+        assert_eq!(pick_broadcasts(vec![], None, None, s("config")), vec!["config"]);
+        assert!(pick_broadcasts(vec![], None, None, None).is_empty());
+    }
+
+    #[test]
+    fn command_line_overrides_environment() {
+        let from_env = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let from_cli = UdpSocket::bind("127.0.0.2:0").unwrap();
+        let env_port = from_env.local_addr().unwrap().port().to_string();
+        let cli_port = from_cli.local_addr().unwrap().port().to_string();
+        let config = env::temp_dir().join(format!("wol-env-config-{}.toml", std::process::id()));
+        fs::write(&config, "").unwrap();
+        let config = config.to_str().unwrap();
+
+        let env = |key: &str| match key {
+            "WOL_BROADCAST" => Some("127.0.0.1".to_string()),
+            "WOL_PORT" => Some(env_port.clone()),
+            _ => None,
+        };
+        let mut a = args(&["-m", "00:1a:2b:3c:4d:5e"]);
+        if cfg!(feature = "config") {
+            a.extend(vec!["--config".to_string(), config.to_string()]);
+        }
+        assert!(run_with_env(&a, io::empty(), &mut io::sink(), &env).is_ok());
+        let mut b = a.clone();
+        b.extend(vec!["-b".to_string(), "127.0.0.2".to_string(), "-P".to_string(), cli_port]);
+        assert!(run_with_env(&b, io::empty(), &mut io::sink(), &env).is_ok());
+
+        let bad = |key: &str| if key == "WOL_PORT" { Some("x".to_string()) } else { None };
+        let err = run_with_env(&a, io::empty(), &mut io::sink(), &bad).unwrap_err();
+        fs::remove_file(config).unwrap();
+        assert_eq!(err.to_string(), "could not parse WOL_PORT: invalid digit found in string");
+
+        let mut buf = [0; 256];
+        for receiver in &[from_env, from_cli] {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(wol::parse_packet(&buf[..len]),
+                       Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+            receiver.set_nonblocking(true).unwrap();
+            assert!(receiver.recv_from(&mut buf).is_err());
+        }
+    }
+
+    #[test]
+    fn strict_refuses_multicast_macs() {
+        let a = args(&["-m", "01:00:5e:00:00:01", "-b", "127.0.0.1", "-n"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let a = args(&["-m", "01:00:5e:00:00:01", "-b", "127.255.255.255", "-n", "--strict"]);
+        let err = run(&a, io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.to_string(), "no packets were sent");
+
+        let a = args(&["-m", "ff:ff:ff:ff:ff:ff", "-b", "127.255.255.255", "-n", "--strict"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+    }
+
+    #[test]
+    fn can_parse_macs_leniently() {
+        let a = args(&["-m", "00 1a 2b 3c 4d 5e", "-b", "127.0.0.1", "-n"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_err());
+
+        let a = args(&["-m", "00 1a 2b 3c 4d 5e", "-b", "127.0.0.1", "-n", "--lenient"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+    }
+
+    #[test]
+    fn return_error_for_huge_sweep() {
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "--sweep", "10.0.0.0/8", "-n"]);
+        let err = run(&a, io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.to_string(),
+                   "10.0.0.0/8 covers 65536 /24 networks, pass --force to wake them all");
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "--sweep", "192.168.0.0/22", "-n"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+    }
+
+    #[test]
+    fn can_wait_for_host_after_sending() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let check = listener.local_addr().unwrap().to_string();
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port, "--wait",
+                       "--check", &check]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port, "--wait"]);
+        let err = run(&a, io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.to_string(), "--wait requires --check");
+    }
+
+    #[test]
+    fn can_send_on_each_interface() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let interfaces = [wol::LocalInterface {
+                              name: "eth9".to_string(),
+                              addr: Ipv4Addr::new(198, 51, 100, 7),
+                              netmask: Ipv4Addr::new(255, 255, 255, 0),
+                          },
+                          wol::LocalInterface {
+                              name: "lo".to_string(),
+                              addr: Ipv4Addr::LOCALHOST,
+                              netmask: Ipv4Addr::BROADCAST,
+                          }];
+
+        let targets = interface_targets(&interfaces, receiver.local_addr().unwrap().port());
+        assert_eq!(targets[0].0.ip(), IpAddr::V4(Ipv4Addr::new(198, 51, 100, 255)));
+
+        let matches = options().parse(&["--all-interfaces"]).unwrap();
+        let round = Round {
+            macs: vec![(0, "00:1a:2b:3c:4d:5e".parse().unwrap())],
+            targets: targets.into_iter()
+                .map(|(t, source)| (t, wol::SendOptions { source, ..Default::default() }))
+                .collect(),
+            password: None,
+            mac_format: wol::MacFormat::default(),
+            template: None,
+            count: 1,
+            interval: Duration::from_millis(0),
+            stagger: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            retries: 0,
+        };
+
+        let mut report = wol::WakeReport::default();
+        wake_round(&matches, &round, &mut Vec::new(), &mut report);
+        assert_eq!(report.failed, 1);
+        assert!(report.failures[0].2.starts_with("198.51.100.255:"));
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
+
+    #[test]
+    fn can_send_to_each_broadcast() {
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = first.local_addr().unwrap().port();
+        let second = UdpSocket::bind(("127.0.0.2", port)).unwrap();
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-b", "zz%eth0", "-b",
+                       "127.0.0.2", "-P", &port.to_string()]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let mut buf = [0; 256];
+        for receiver in &[first, second] {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(wol::parse_packet(&buf[..len]),
+                       Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+        }
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "zz%eth0"]);
+        let err = run(&a, io::empty(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.to_string(), "none of the broadcast addresses could be used");
+    }
+
+    #[test]
+    fn can_run_against_loopback() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
+
+    #[test]
+    fn can_send_several_packets() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "-P", &port, "-c", "3",
+                       "-i", "1ms"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let mut buf = [0; 256];
+        for _ in 0..3 {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(wol::parse_packet(&buf[..len]),
+                       Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+        }
+        receiver.set_nonblocking(true).unwrap();
+        assert!(receiver.recv_from(&mut buf).is_err());
+    }
+
+    #[test]
+    fn can_disable_color() {
+        let mut out = Vec::new();
+        let matches = options().parse(&["--color", "never"]).unwrap();
+        let color = use_color(&matches).unwrap();
+        write_record(&mut out, Level::Info, SUCCESS, "packet sent Ok", color).unwrap();
+        write_record(&mut out, Level::Error, "wol", "could not send", color).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "packet sent Ok\ncould not send\n");
+
+        let mut out = Vec::new();
+        let matches = options().parse(&["--color", "always"]).unwrap();
+        write_record(&mut out, Level::Error, "wol", "could not send", use_color(&matches).unwrap())
+            .unwrap();
+        assert!(out.starts_with(b"\x1b["));
+
+        let matches = options().parse(&["--color", "always", "--json"]).unwrap();
+        assert!(!use_color(&matches).unwrap());
+
+        let matches = options().parse(&["--color", "sometimes"]).unwrap();
+        assert!(use_color(&matches).is_err());
+    }
+
+    #[test]
+    fn can_render_template() {
+        let template = check_template("woke {mac} on {broadcast}:{port} ({bytes} bytes)").unwrap();
+        let target = "192.168.1.255:9".parse().unwrap();
+        assert_eq!(render_template(&template, "00:1a:2b:3c:4d:5e", &target, 102),
+                   "woke 00:1a:2b:3c:4d:5e on 192.168.1.255:9 (102 bytes)");
+
+        let err = check_template("woke {host}").unwrap_err();
+        assert!(err.starts_with("unknown placeholder {host}"));
+        assert!(check_template("woke {mac").is_err());
+
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "127.0.0.1", "--template", "{ip}"]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_err());
+    }
+
+    #[test]
+    fn can_stagger_sends() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port().to_string();
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-m", "00:1a:2b:3c:4d:5f", "-m",
+                       "00:1a:2b:3c:4d:60", "-b", "127.0.0.1", "-P", &port, "--stagger", "0",
+                       "--jitter", "0"]);
+        let start = Instant::now();
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+        assert!(start.elapsed() < Duration::from_millis(50));
+
+        let mut buf = [0; 256];
+        for _ in 0..3 {
+            assert_eq!(receiver.recv_from(&mut buf).unwrap().0, 102);
+        }
+
+        let stagger = Duration::from_millis(100);
+        let jitter = Duration::from_millis(30);
+        for &random in &[0, 1, 12345, 30_000_000, 60_000_000, 987_654_321, u64::MAX] {
+            let delay = jittered(stagger, jitter, random);
+            assert!(delay >= Duration::from_millis(70) && delay <= Duration::from_millis(130));
+        }
+        assert_eq!(jittered(stagger, jitter, 0), Duration::from_millis(70));
+        assert_eq!(jittered(stagger, jitter, 60_000_000), Duration::from_millis(130));
+        assert_eq!(jittered(Duration::from_millis(10), jitter, 0), Duration::from_millis(0));
+        assert_eq!(jittered(Duration::from_millis(0), Duration::from_millis(0), 42),
+                   Duration::from_millis(0));
+
+        let huge = Duration::from_secs(12_000_000_000);
+        assert_eq!(jittered(Duration::MAX, huge, u64::MAX), Duration::MAX - huge);
+        assert_eq!(jittered(stagger, huge, 0), Duration::from_millis(0));
+    }
+
+    #[test]
+    fn can_write_pcap() {
+        let path = env::temp_dir().join(format!("wol-pcap-test-{}.pcap", std::process::id()));
+        let a = args(&["-m", "00:1a:2b:3c:4d:5e", "-b", "192.168.1.255", "--pcap",
+                       path.to_str().unwrap()]);
+        assert!(run(&a, io::empty(), &mut io::sink()).is_ok());
+
+        let pcap = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let frame_len = 14 + 20 + 8 + 102;
+        assert_eq!(pcap.len(), 24 + 16 + frame_len);
+        assert_eq!(pcap[0..4], [0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(pcap[20..24], 1u32.to_le_bytes());
+        assert_eq!(pcap[32..36], (frame_len as u32).to_le_bytes());
+        assert_eq!(pcap[36..40], (frame_len as u32).to_le_bytes());
+        assert_eq!(wol::parse_packet(&pcap[40 + 42..]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
+
+    #[test]
+    fn can_parse_durations() {
+        assert_eq!(parse_duration("250"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("30h").is_err());
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("999999999999999999m").is_err());
+    }
+
+    #[test]
+    fn can_run_one_daemon_round() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let matches = options().parse(&["--daemon"]).unwrap();
+        let round = Round {
+            macs: vec![(0, "00:1a:2b:3c:4d:5e".parse().unwrap())],
+            targets: vec![(receiver.local_addr().unwrap(), wol::SendOptions::default())],
+            password: None,
+            mac_format: wol::MacFormat::default(),
+            template: None,
+            count: 2,
+            interval: Duration::from_millis(1),
+            stagger: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            retries: 0,
+        };
+
+        let running = AtomicBool::new(true);
+        let mut reports = Vec::new();
+        let (rounds, sent) = run_daemon(&running, Duration::from_secs(30), || {
+            let mut report = wol::WakeReport::default();
+            running.store(false, Ordering::SeqCst);
+            wake_round(&matches, &round, &mut reports, &mut report)
+        });
+        assert_eq!((rounds, sent), (1, 2));
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
 }
@@ -0,0 +1,1343 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::net::{UdpSocket, TcpStream, SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr,
+               Ipv6Addr, ToSocketAddrs};
+
+use crate::{build_packet, build_packet_with_password, is_magic_packet, looks_like_broadcast,
+            parse_packet, LocalInterface, Mac, ParseError, Password, WolError};
+
+#[cfg(test)]
+mod test {
+    use super::{bind_socket, broadcast_for_host, check_whole, find_interface, local_interfaces,
+                parse_ethers, parse_mac_list, resolve_destination, resolve_target, self_test,
+                send_packet, send_packet_on, send_packet_ports, send_packet_repeated,
+                send_packet_retry, send_packet_retry_with, send_packet_with, wait_for_host,
+                wake_all, wake_all_with, wake_from_file, DEFAULT_TIMEOUT, SendError, SendOptions,
+                WakeRequest, WolSocket};
+    use crate::{build_packet, parse_packet, LocalInterface, Mac, ParseError, Password, WolError};
+    use std::env;
+    use std::fs;
+    use std::io::{self, Cursor};
+    use std::time::Duration;
+    use std::net::{UdpSocket, TcpListener, SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr,
+                   Ipv6Addr};
+
+    #[test]
+    fn can_list_local_interfaces() {
+        assert!(local_interfaces().unwrap().iter().all(|i| !i.addr.is_loopback()));
+    }
+
+    #[test]
+    fn can_find_interface_by_name() {
+        let lo = find_interface("lo").unwrap();
+        assert_eq!(lo.addr, Ipv4Addr::new(127, 0, 0, 1));
+        assert_eq!(lo.broadcast(), Ipv4Addr::new(127, 255, 255, 255));
+
+        let err = find_interface("no-such-dev0").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn can_parse_mac_list() {
+        let file = ["# lab machines",
+                    "ff:ff:ff:ff:ff:ff",
+                    "",
+                    "  00:1a:2b:3c:4d:5e\t",
+                    "not-a-mac",
+                    "#00:00:00:00:00:00",
+                    "01-02-03-04-05-06"]
+            .join("\n");
+
+        let entries = parse_mac_list(Cursor::new(file.as_bytes())).unwrap();
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0],
+                   (2, "ff:ff:ff:ff:ff:ff".to_string(), Ok(Mac([255, 255, 255, 255, 255, 255]))));
+        assert_eq!(entries[1],
+                   (4,
+                    "00:1a:2b:3c:4d:5e".to_string(),
+                    Ok(Mac([0, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]))));
+        assert_eq!(entries[2],
+                   (5, "not-a-mac".to_string(), Err(ParseError::InvalidInput)));
+        assert_eq!(entries[3],
+                   (7, "01-02-03-04-05-06".to_string(), Ok(Mac([1, 2, 3, 4, 5, 6]))));
+    }
+
+    #[test]
+    fn can_parse_ethers() {
+        let file = "# /etc/ethers\n\
+                    00:1a:2b:3c:4d:5e\tofficepc\n\
+                    \n\
+                    zz:zz:zz:zz:zz:zz broken\n\
+                    01-02-03-04-05-06   nas.lan  # in the cupboard\n\
+                    ff:ff:ff:ff:ff:ff\n";
+
+        let hosts = parse_ethers(Cursor::new(file.as_bytes())).unwrap();
+        assert_eq!(hosts,
+                   vec![(Mac([0, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]), "officepc".to_string()),
+                        (Mac([1, 2, 3, 4, 5, 6]), "nas.lan".to_string())]);
+    }
+
+    #[test]
+    fn can_find_broadcast_for_host() {
+        let interfaces = [LocalInterface {
+                              name: "eth0".to_string(),
+                              addr: Ipv4Addr::new(192, 168, 1, 10),
+                              netmask: Ipv4Addr::new(255, 255, 255, 0),
+                          },
+                          LocalInterface {
+                              name: "lo".to_string(),
+                              addr: Ipv4Addr::new(127, 0, 0, 5),
+                              netmask: Ipv4Addr::new(255, 0, 0, 0),
+                          }];
+
+        assert_eq!(broadcast_for_host("192.168.1.50", &interfaces),
+                   Some(Ipv4Addr::new(192, 168, 1, 255)));
+        assert_eq!(broadcast_for_host("127.0.0.1", &interfaces),
+                   Some(Ipv4Addr::new(127, 255, 255, 255)));
+        assert_eq!(broadcast_for_host("10.0.0.1", &interfaces), None);
+        assert_eq!(broadcast_for_host("192.168.1.50", &[]), None);
+    }
+
+    #[test]
+    fn can_parse_mac_list_with_inline_comments() {
+        let file = "ff:ff:ff:ff:ff:ff # living room TV\n\
+                    \t# nas\n\
+                    00:1a:2b:3c:4d:5e#office\n\
+                    zz # broken\n";
+
+        let entries = parse_mac_list(Cursor::new(file.as_bytes())).unwrap();
+        assert_eq!(entries,
+                   vec![(1, "ff:ff:ff:ff:ff:ff".to_string(), Ok(Mac::broadcast())),
+                        (3,
+                         "00:1a:2b:3c:4d:5e".to_string(),
+                         Ok(Mac([0, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]))),
+                        (4, "zz".to_string(), Err(ParseError::InvalidInput))]);
+    }
+
+    #[test]
+    fn can_resolve_target() {
+        assert_eq!(resolve_target("192.168.1.255").unwrap(),
+                   IpAddr::V4(Ipv4Addr::new(192, 168, 1, 255)));
+        assert_eq!(resolve_target("::1").unwrap(),
+                   IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1)));
+        assert_eq!(resolve_target("localhost").unwrap(),
+                   IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+        assert!(resolve_target("no-such-host.invalid").is_err());
+    }
+
+    #[test]
+    fn can_resolve_destination_with_zone() {
+        let ff02 = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+
+        assert_eq!(resolve_destination("ff02::1%3", 9).unwrap(),
+                   SocketAddr::V6(SocketAddrV6::new(ff02, 9, 0, 3)));
+        assert_eq!(resolve_destination("192.168.1.255", 7).unwrap(),
+                   SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 255)), 7));
+
+        if cfg!(target_os = "linux") {
+            match resolve_destination("ff02::1%lo", 9).unwrap() {
+                SocketAddr::V6(addr) => {
+                    assert_eq!((*addr.ip(), addr.port()), (ff02, 9));
+                    assert_ne!(addr.scope_id(), 0);
+                }
+                addr => panic!("expected an IPv6 destination, got {}", addr),
+            }
+        }
+
+        let err = resolve_destination("ff02::1%no-such-if0", 9).unwrap_err();
+        assert_eq!(err.to_string(), "unknown interface no-such-if0");
+        assert!(resolve_destination("ff02::1%", 9).is_err());
+        assert!(resolve_destination("192.168.1.255%lo", 9).is_err());
+    }
+
+    #[test]
+    fn can_send_packet_loopback() {
+        let raddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 9));
+        assert_eq!(send_packet(&[0xff; 102], &raddr).unwrap(), 102);
+    }
+
+    #[test]
+    fn sends_whole_packet() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        assert_eq!(send_packet(&[0xff; 108], &raddr).unwrap(), 108);
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(len, 108);
+    }
+
+    #[test]
+    fn can_send_packet_on_caller_socket() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = match receiver.local_addr().unwrap() {
+            SocketAddr::V4(dest) => dest,
+            SocketAddr::V6(_) => unreachable!(),
+        };
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let packet = build_packet(&Mac::broadcast()).unwrap();
+        assert_eq!(send_packet_on(&socket, &packet, &dest).unwrap(), 102);
+        assert_eq!(send_packet_on(&socket, &packet, &dest).unwrap(), 102);
+
+        let mut buf = [0; 256];
+        for _ in 0..2 {
+            let (len, src) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..len], &packet[..]);
+            assert_eq!(src, socket.local_addr().unwrap());
+        }
+    }
+
+    #[test]
+    fn full_send_is_not_a_short_write() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let result = send_packet(&build_packet(&Mac::broadcast()).unwrap(),
+                                 &receiver.local_addr().unwrap());
+        assert!(result.as_ref().err().and_then(|e| e.downcast_ref::<WolError>()).is_none());
+        assert_eq!(result.unwrap(), 102);
+
+        assert_eq!(WolError::ShortWrite { expected: 102, actual: 64 }.to_string(),
+                   "short write: sent 64 of 102 bytes");
+        assert_eq!(check_whole(&[0xff; 102], 102).unwrap(), 102);
+        assert!(matches!(check_whole(&[0xff; 102], 64),
+                         Err(WolError::ShortWrite { expected: 102, actual: 64 })));
+    }
+
+    #[test]
+    fn can_send_packet_with_retry() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let sent = send_packet_retry(&[0xff; 102], &raddr, 3, Duration::from_millis(1));
+        assert_eq!(sent.unwrap(), 102);
+
+        let mut buf = [0; 256];
+        assert_eq!(receiver.recv_from(&mut buf).unwrap().0, 102);
+        receiver.set_nonblocking(true).unwrap();
+        assert!(receiver.recv_from(&mut buf).is_err());
+    }
+
+    #[test]
+    fn return_error_after_last_retry() {
+        let raddr: SocketAddr = "127.0.0.1:9".parse().unwrap();
+        let opts = SendOptions {
+            source: Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))),
+            ..SendOptions::default()
+        };
+        assert!(send_packet_retry_with(&[0xff; 102], &raddr, &opts, 2, Duration::from_millis(1))
+            .is_err());
+    }
+
+    #[test]
+    fn can_send_packet_repeatedly() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let sent = send_packet_repeated(&[0xff; 102],
+                                        &raddr,
+                                        &SendOptions::default(),
+                                        3,
+                                        Duration::from_millis(1));
+        assert_eq!(sent.unwrap(), 306);
+
+        let mut buf = [0; 256];
+        for _ in 0..3 {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(len, 102);
+        }
+    }
+
+    #[test]
+    fn can_build_wake_request() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+        let password: Password = "01:02:03:04:05:06".parse().unwrap();
+
+        let wake = WakeRequest::new(mac)
+            .broadcast(Ipv4Addr::LOCALHOST)
+            .port(raddr.port())
+            .count(2)
+            .interval(Duration::from_millis(1))
+            .password(password)
+            .build()
+            .unwrap();
+        assert_eq!(wake.packet().len(), 108);
+        assert_eq!(&wake.packet()[102..], password.as_bytes());
+        assert_eq!(wake.target(), raddr);
+        assert_eq!(wake.send().unwrap(), 216);
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(len, 108);
+
+        assert!(WakeRequest::new(mac).count(0).build().is_err());
+        assert!(WakeRequest::new(mac).port(0).build().is_err());
+        assert!(WakeRequest::new(mac).source(Ipv6Addr::LOCALHOST).build().is_err());
+        assert_eq!(WakeRequest::new(mac).build().unwrap().target(),
+                   "255.255.255.255:9".parse().unwrap());
+    }
+
+    #[test]
+    fn can_send_packet_from_source_address() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let opts = SendOptions {
+            source: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))),
+            ..SendOptions::default()
+        };
+        assert_eq!(send_packet_with(&[0xff; 102], &raddr, &opts).unwrap(), 102);
+
+        let mut buf = [0; 256];
+        let (_, src) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(src.ip(), IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
+    }
+
+    #[test]
+    fn can_send_packet_from_source_port() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let port = UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let opts = SendOptions {
+            source: Some(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1))),
+            source_port: port,
+            ..SendOptions::default()
+        };
+        assert_eq!(send_packet_with(&[0xff; 102], &raddr, &opts).unwrap(), 102);
+
+        let mut buf = [0; 256];
+        let (_, src) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(src.port(), port);
+
+        let held = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let taken = held.local_addr().unwrap();
+        let opts = SendOptions {
+            source: Some(taken.ip()),
+            source_port: taken.port(),
+            ..SendOptions::default()
+        };
+        let err = send_packet_with(&[0xff; 102], &raddr, &opts).unwrap_err();
+        assert!(err.to_string().starts_with(&format!("could not bind {}: ", taken)));
+    }
+
+    #[test]
+    fn can_send_packet_with_ttl() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let opts = SendOptions { ttl: Some(1), ..SendOptions::default() };
+        assert_eq!(bind_socket(&raddr, &opts).unwrap().ttl().unwrap(), 1);
+        assert_eq!(send_packet_with(&[0xff; 102], &raddr, &opts).unwrap(), 102);
+
+        let mut buf = [0; 256];
+        assert_eq!(receiver.recv_from(&mut buf).unwrap().0, 102);
+
+        let group = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 1), 9));
+        let socket = bind_socket(&group, &SendOptions::default()).unwrap();
+        assert_eq!(socket.multicast_ttl_v4().unwrap(), 1);
+
+        let opts = SendOptions { ttl: Some(0), ..SendOptions::default() };
+        assert_eq!(bind_socket(&group, &opts).unwrap().multicast_ttl_v4().unwrap(), 0);
+
+        let broadcast = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(255, 255, 255, 255), 9));
+        let socket = bind_socket(&broadcast, &SendOptions::default()).unwrap();
+        assert_eq!(socket.ttl().unwrap(), 1);
+    }
+
+    #[test]
+    fn can_reuse_socket_for_many_sends() {
+        let socket = WolSocket::bind().unwrap();
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let dest = match receiver.local_addr().unwrap() {
+            SocketAddr::V4(a) => a,
+            SocketAddr::V6(_) => unreachable!(),
+        };
+
+        let mut buf = [0; 256];
+        for _ in 0..3 {
+            assert_eq!(socket.send(&[0xff; 102], &dest).unwrap(), 102);
+            let (len, src) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(len, 102);
+            assert_eq!(src.port(), socket.local_addr().unwrap().port());
+        }
+    }
+
+    #[test]
+    fn can_send_packet_with_short_timeout() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let opts = SendOptions { timeout: Duration::from_millis(1), ..SendOptions::default() };
+        assert_eq!(send_packet_with(&[0xff; 102], &raddr, &opts).unwrap(), 102);
+        assert_eq!(SendOptions::default().timeout, DEFAULT_TIMEOUT);
+    }
+
+    #[test]
+    fn self_test_passes() {
+        assert_eq!(self_test().unwrap().to_string(), "02:00:00:00:00:01");
+    }
+
+    #[test]
+    fn can_wait_for_host_that_is_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let elapsed = wait_for_host(&addr, Duration::from_secs(5), Duration::from_secs(1)).unwrap();
+        assert!(elapsed < Duration::from_secs(1));
+
+        drop(listener);
+        let err = wait_for_host(&addr, Duration::from_millis(50), Duration::from_millis(10))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "timed out waiting for host to come online");
+    }
+
+    #[test]
+    fn can_map_io_errors_to_send_errors() {
+        let err = io::Error::new(io::ErrorKind::PermissionDenied, "broadcast not allowed");
+        assert!(matches!(SendError::from(err), SendError::PermissionDenied));
+
+        let err = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(matches!(SendError::from(err), SendError::Timeout));
+
+        let err = io::Error::new(io::ErrorKind::WouldBlock, "would block");
+        assert!(matches!(SendError::from_io(err, true), SendError::WouldBlock));
+
+        let err = io::Error::other("boom");
+        assert_eq!(SendError::from(err).to_string(), "boom");
+        assert_eq!(SendError::Timeout.to_string(), "timed out sending magic packet");
+        assert_eq!(SendError::WouldBlock.to_string(), "sending magic packet would block");
+    }
+
+    #[test]
+    fn can_send_packet_nonblocking() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        let opts = SendOptions { nonblocking: true, ..SendOptions::default() };
+        assert_eq!(send_packet_with(&[0xff; 102], &raddr, &opts).unwrap(), 102);
+
+        let mut buf = [0; 256];
+        assert_eq!(receiver.recv_from(&mut buf).unwrap().0, 102);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn can_send_packet_async_loopback() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let raddr = receiver.local_addr().unwrap();
+        assert_eq!(super::send_packet_async(&[0xff; 102], &raddr).await.unwrap(), 102);
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(len, 102);
+    }
+
+    #[test]
+    fn can_wake_all_loopback_targets() {
+        let receivers: Vec<UdpSocket> =
+            (0..3).map(|_| UdpSocket::bind("127.0.0.1:0").unwrap()).collect();
+        let targets: Vec<(Mac, SocketAddr)> = receivers.iter()
+            .enumerate()
+            .map(|(i, r)| (Mac([0, 0, 0, 0, 0, i as u8]), r.local_addr().unwrap()))
+            .collect();
+
+        let results = wake_all_with(&targets, 2);
+        assert_eq!(results.len(), 3);
+        for r in &results {
+            assert_eq!(*r.as_ref().unwrap(), 102);
+        }
+
+        let mut buf = [0; 256];
+        for (i, receiver) in receivers.iter().enumerate() {
+            let (len, _) = receiver.recv_from(&mut buf).unwrap();
+            assert_eq!(parse_packet(&buf[..len]), Some(Mac([0, 0, 0, 0, 0, i as u8])));
+        }
+
+        assert!(wake_all(&targets).iter().all(|r| r.is_ok()));
+        assert!(wake_all(&[]).is_empty());
+    }
+
+    #[test]
+    fn can_wake_from_file() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let path = env::temp_dir().join(format!("wol-wake-file-test-{}", std::process::id()));
+        fs::write(&path, "00:1a:2b:3c:4d:5e\nzz\n\n00:1a:2b:3c:4d:5f\n").unwrap();
+
+        let report = wake_from_file(&path, &receiver.local_addr().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((report.succeeded, report.failed), (2, 1));
+        assert_eq!((report.failures[0].0, &report.failures[0].1[..]), (2, "zz"));
+
+        let mut buf = [0; 256];
+        for _ in 0..2 {
+            assert_eq!(receiver.recv_from(&mut buf).unwrap().0, 102);
+        }
+
+        assert!(wake_from_file("/nonexistent/macs.txt", &receiver.local_addr().unwrap()).is_err());
+    }
+
+    #[test]
+    fn can_send_packet_to_several_ports() {
+        let first = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let second = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let ports = [first.local_addr().unwrap().port(), second.local_addr().unwrap().port()];
+        let target = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 9));
+
+        let results = send_packet_ports(&[0xff; 102], &target, &ports, &SendOptions::default())
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| *r.as_ref().unwrap() == 102));
+
+        let mut buf = [0; 256];
+        let (_, from_first) = first.recv_from(&mut buf).unwrap();
+        let (_, from_second) = second.recv_from(&mut buf).unwrap();
+        assert_eq!(from_first, from_second);
+    }
+
+    #[test]
+    fn socket_has_broadcast_enabled() {
+        let raddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(255, 255, 255, 255), 9));
+        assert!(bind_socket(&raddr, &SendOptions::default()).unwrap().broadcast().unwrap());
+    }
+
+    #[test]
+    fn can_send_packet_loopback_v6() {
+        let raddr = SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1),
+                                                   9,
+                                                   0,
+                                                   0));
+        assert_eq!(send_packet(&[0xff; 102], &raddr).unwrap(), 102);
+    }
+}
+
+#[derive(Debug)]
+pub enum SendError {
+    NetworkUnreachable,
+    PermissionDenied,
+    Timeout,
+    WouldBlock,
+    Other(io::Error),
+}
+
+impl SendError {
+    fn from_io(e: io::Error, nonblocking: bool) -> SendError {
+        match e.kind() {
+            io::ErrorKind::NetworkUnreachable | io::ErrorKind::HostUnreachable => {
+                SendError::NetworkUnreachable
+            }
+            io::ErrorKind::PermissionDenied => SendError::PermissionDenied,
+            io::ErrorKind::WouldBlock if nonblocking => SendError::WouldBlock,
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => SendError::Timeout,
+            _ => SendError::Other(e),
+        }
+    }
+}
+
+impl From<io::Error> for SendError {
+    fn from(e: io::Error) -> SendError {
+        SendError::from_io(e, false)
+    }
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            SendError::NetworkUnreachable => "network is unreachable",
+            SendError::PermissionDenied => "permission denied (is broadcast allowed?)",
+            SendError::Timeout => "timed out sending magic packet",
+            SendError::WouldBlock => "sending magic packet would block",
+            SendError::Other(ref e) => return e.fmt(f),
+        };
+        f.write_str(msg)
+    }
+}
+
+impl Error for SendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SendError::Other(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+pub type MacListEntry = (usize, String, Result<Mac, ParseError>);
+
+pub fn parse_mac_list<R: BufRead>(reader: R) -> io::Result<Vec<MacListEntry>> {
+    let mut entries = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = match line.find('#') {
+            Some(i) => line[..i].trim(),
+            None => line.trim(),
+        };
+
+        if line.is_empty() {
+            continue;
+        }
+
+        entries.push((i + 1, line.to_string(), line.parse()));
+    }
+
+    Ok(entries)
+}
+
+pub fn parse_ethers<R: BufRead>(reader: R) -> io::Result<Vec<(Mac, String)>> {
+    let mut hosts = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => &line,
+        };
+
+        let mut fields = line.split_whitespace();
+        let (mac, name) = match (fields.next(), fields.next()) {
+            (Some(mac), Some(name)) => (mac, name),
+            (None, _) => continue,
+            (Some(_), None) => {
+                warn!("skipping line {} of ethers file: no host name", i + 1);
+                continue;
+            }
+        };
+
+        match mac.parse() {
+            Ok(mac) => hosts.push((mac, name.to_string())),
+            Err(e) => warn!("skipping line {} of ethers file ({}): {}", i + 1, mac, e),
+        }
+    }
+
+    Ok(hosts)
+}
+
+pub fn broadcast_for_host(host: &str, interfaces: &[LocalInterface]) -> Option<Ipv4Addr> {
+    let ip = match resolve_target(host) {
+        Ok(IpAddr::V4(ip)) => ip,
+        _ => return None,
+    };
+
+    interfaces.iter()
+        .find(|i| u32::from(i.addr) & u32::from(i.netmask) == u32::from(ip) & u32::from(i.netmask))
+        .map(|i| i.broadcast())
+}
+
+pub fn local_interfaces() -> io::Result<Vec<LocalInterface>> {
+    Ok(if_addrs::get_if_addrs()?
+        .into_iter()
+        .filter(|i| !i.is_loopback())
+        .filter_map(|i| match i.addr {
+            if_addrs::IfAddr::V4(v4) => {
+                Some(LocalInterface {
+                    name: i.name,
+                    addr: v4.ip,
+                    netmask: v4.netmask,
+                })
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+pub fn find_interface(name: &str) -> io::Result<LocalInterface> {
+    let addrs: Vec<_> = if_addrs::get_if_addrs()?.into_iter().filter(|i| i.name == name).collect();
+
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound,
+                                  format!("no interface named {}", name)));
+    }
+
+    addrs.into_iter()
+        .find_map(|i| match i.addr {
+            if_addrs::IfAddr::V4(v4) => {
+                Some(LocalInterface {
+                    name: i.name,
+                    addr: v4.ip,
+                    netmask: v4.netmask,
+                })
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput,
+                           format!("{} has no IPv4 address", name))
+        })
+}
+
+pub fn resolve_destination(host: &str, port: u16) -> io::Result<SocketAddr> {
+    let (addr, zone) = match host.find('%') {
+        Some(i) => (&host[..i], &host[i + 1..]),
+        None => return resolve_target(host).map(|ip| SocketAddr::new(ip, port)),
+    };
+
+    let ip: Ipv6Addr = addr.parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput,
+                       format!("zone ids are only valid on IPv6 addresses, not {}", addr))
+    })?;
+
+    Ok(SocketAddr::V6(SocketAddrV6::new(ip, port, 0, zone_index(zone)?)))
+}
+
+fn zone_index(zone: &str) -> io::Result<u32> {
+    if zone.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "empty zone id"));
+    }
+
+    if let Ok(index) = zone.parse() {
+        return Ok(index);
+    }
+
+    match interface_index(zone) {
+        0 => Err(io::Error::new(io::ErrorKind::NotFound, format!("unknown interface {}", zone))),
+        index => Ok(index),
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> u32 {
+    match std::ffi::CString::new(name) {
+        Ok(name) => unsafe { libc::if_nametoindex(name.as_ptr()) },
+        Err(_) => 0,
+    }
+}
+
+#[cfg(not(unix))]
+fn interface_index(_: &str) -> u32 {
+    0
+}
+
+pub fn resolve_target(host: &str) -> io::Result<IpAddr> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    (host, 0)
+        .to_socket_addrs()?
+        .find(|a| a.is_ipv4())
+        .map(|a| a.ip())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound,
+                           format!("no IPv4 address found for {}", host))
+        })
+}
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct SendOptions {
+    pub source: Option<IpAddr>,
+    pub source_port: u16,
+    pub timeout: Duration,
+    pub nonblocking: bool,
+    // IP TTL or IPv6 hop limit; unset means 1 for broadcast and multicast targets and the OS
+    // default otherwise.
+    pub ttl: Option<u8>,
+}
+
+impl Default for SendOptions {
+    fn default() -> SendOptions {
+        SendOptions {
+            source: None,
+            source_port: 0,
+            timeout: DEFAULT_TIMEOUT,
+            nonblocking: false,
+            ttl: None,
+        }
+    }
+}
+
+fn local_addr(r: &SocketAddr, opts: &SendOptions) -> SocketAddr {
+    let port = opts.source_port;
+
+    match (*r, opts.source) {
+        (_, Some(source)) => SocketAddr::new(source, port),
+        (SocketAddr::V4(_), None) => {
+            SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), port))
+        }
+        (SocketAddr::V6(_), None) => {
+            SocketAddr::V6(SocketAddrV6::new(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0), port, 0, 0))
+        }
+    }
+}
+
+fn bind_socket(r: &SocketAddr, opts: &SendOptions) -> io::Result<UdpSocket> {
+    let local = local_addr(r, opts);
+    let socket = UdpSocket::bind(local).map_err(|e| {
+        if opts.source_port == 0 {
+            e
+        } else {
+            io::Error::new(e.kind(), format!("could not bind {}: {}", local, e))
+        }
+    })?;
+    socket.set_write_timeout(Some(opts.timeout))?;
+    socket.set_nonblocking(opts.nonblocking)?;
+
+    if r.is_ipv4() {
+        socket.set_broadcast(true)?;
+    }
+
+    match opts.ttl {
+        Some(ttl) => set_ttl(&socket, r, ttl)?,
+        None if is_local_only(r) => {
+            // The implicit default is best effort where the platform has no hop limit option.
+            match set_ttl(&socket, r, 1) {
+                Err(ref e) if e.kind() == io::ErrorKind::Unsupported => {
+                    debug!("could not set a hop limit for {}: {}", r, e);
+                }
+                result => result?,
+            }
+        }
+        None => {}
+    }
+
+    Ok(socket)
+}
+
+fn is_local_only(r: &SocketAddr) -> bool {
+    match *r {
+        SocketAddr::V4(ref v4) => looks_like_broadcast(v4.ip()),
+        SocketAddr::V6(ref v6) => v6.ip().is_multicast(),
+    }
+}
+
+fn set_ttl(socket: &UdpSocket, r: &SocketAddr, ttl: u8) -> io::Result<()> {
+    match *r {
+        SocketAddr::V4(ref v4) if v4.ip().is_multicast() => {
+            socket.set_multicast_ttl_v4(u32::from(ttl))
+        }
+        SocketAddr::V4(_) => socket.set_ttl(u32::from(ttl)),
+        SocketAddr::V6(ref v6) => set_hop_limit_v6(socket, v6.ip().is_multicast(), ttl),
+    }
+}
+
+#[cfg(unix)]
+fn set_hop_limit_v6(socket: &UdpSocket, multicast: bool, hops: u8) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let option = if multicast {
+        libc::IPV6_MULTICAST_HOPS
+    } else {
+        libc::IPV6_UNICAST_HOPS
+    };
+    let hops = libc::c_int::from(hops);
+
+    let ret = unsafe {
+        libc::setsockopt(socket.as_raw_fd(),
+                         libc::IPPROTO_IPV6,
+                         option,
+                         &hops as *const libc::c_int as *const libc::c_void,
+                         std::mem::size_of::<libc::c_int>() as libc::socklen_t)
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(unix))]
+fn set_hop_limit_v6(_: &UdpSocket, _: bool, _: u8) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "IPv6 hop limits need a unix platform"))
+}
+
+pub fn send_packet(p: &[u8], r: &SocketAddr) -> Result<usize, Box<dyn Error>> {
+    send_packet_with(p, r, &SendOptions::default())
+}
+
+fn send_on(socket: &UdpSocket,
+           p: &[u8],
+           r: &SocketAddr,
+           nonblocking: bool)
+           -> Result<usize, SendError> {
+    let sent = socket.send_to(p, r).map_err(|e| SendError::from_io(e, nonblocking))?;
+
+    debug!("sent {} bytes to {}", sent, r);
+
+    Ok(sent)
+}
+
+#[derive(Debug)]
+pub struct WolSocket {
+    socket: UdpSocket,
+    nonblocking: bool,
+}
+
+impl WolSocket {
+    pub fn bind() -> io::Result<WolSocket> {
+        WolSocket::bind_with(&SendOptions::default())
+    }
+
+    pub fn bind_with(opts: &SendOptions) -> io::Result<WolSocket> {
+        let any = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0));
+
+        WolSocket::bind_for(&any, opts)
+    }
+
+    // Binds with the socket options (TTL, broadcast) suited to one destination.
+    fn bind_for(r: &SocketAddr, opts: &SendOptions) -> io::Result<WolSocket> {
+        Ok(WolSocket {
+            socket: bind_socket(r, opts)?,
+            nonblocking: opts.nonblocking,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn send(&self, packet: &[u8], dest: &SocketAddrV4) -> Result<usize, SendError> {
+        send_whole(&self.socket, packet, &SocketAddr::V4(*dest), self.nonblocking)
+    }
+}
+
+pub fn send_packet_with(p: &[u8],
+                        r: &SocketAddr,
+                        opts: &SendOptions)
+                        -> Result<usize, Box<dyn Error>> {
+    match *r {
+        SocketAddr::V4(ref dest) => Ok(WolSocket::bind_for(r, opts)?.send(p, dest)?),
+        SocketAddr::V6(_) => Ok(send_whole(&bind_socket(r, opts)?, p, r, opts.nonblocking)?),
+    }
+}
+
+pub fn send_packet_on(socket: &UdpSocket,
+                      packet: &[u8],
+                      dest: &SocketAddrV4)
+                      -> Result<usize, Box<dyn Error>> {
+    Ok(send_whole(socket, packet, &SocketAddr::V4(*dest), false)?)
+}
+
+// A short write is reported as a WriteZero error carrying WolError::ShortWrite.
+fn send_whole(socket: &UdpSocket,
+              p: &[u8],
+              r: &SocketAddr,
+              nonblocking: bool)
+              -> Result<usize, SendError> {
+    let sent = send_on(socket, p, r, nonblocking)?;
+
+    check_whole(p, sent).map_err(|e| SendError::Other(io::Error::new(io::ErrorKind::WriteZero, e)))
+}
+
+fn check_whole(p: &[u8], sent: usize) -> Result<usize, WolError> {
+    if sent != p.len() {
+        return Err(WolError::ShortWrite {
+            expected: p.len(),
+            actual: sent,
+        });
+    }
+
+    Ok(sent)
+}
+
+pub fn send_packet_ports(p: &[u8],
+                         target: &SocketAddr,
+                         ports: &[u16],
+                         opts: &SendOptions)
+                         -> io::Result<Vec<Result<usize, SendError>>> {
+    send_packet_ports_retry(p, target, ports, opts, 1, Duration::from_millis(0))
+}
+
+// Every port, and every retry, goes out on the one socket bound for the target.
+pub fn send_packet_ports_retry(p: &[u8],
+                               target: &SocketAddr,
+                               ports: &[u16],
+                               opts: &SendOptions,
+                               max_attempts: u32,
+                               base_delay: Duration)
+                               -> io::Result<Vec<Result<usize, SendError>>> {
+    let socket = bind_socket(target, opts)?;
+
+    Ok(ports.iter()
+        .map(|&port| {
+            let mut r = *target;
+            r.set_port(port);
+            retry(&r, max_attempts, base_delay, || send_whole(&socket, p, &r, opts.nonblocking))
+        })
+        .collect())
+}
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const LINKTYPE_ETHERNET: u32 = 1;
+
+pub fn write_pcap<W: Write>(mut w: W, frames: &[Vec<u8>]) -> io::Result<()> {
+    w.write_all(&PCAP_MAGIC.to_le_bytes())?;
+    w.write_all(&2u16.to_le_bytes())?;
+    w.write_all(&4u16.to_le_bytes())?;
+    w.write_all(&[0; 8])?;
+    w.write_all(&65535u32.to_le_bytes())?;
+    w.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    for frame in frames {
+        w.write_all(&(now.as_secs() as u32).to_le_bytes())?;
+        w.write_all(&now.subsec_micros().to_le_bytes())?;
+        w.write_all(&(frame.len() as u32).to_le_bytes())?;
+        w.write_all(&(frame.len() as u32).to_le_bytes())?;
+        w.write_all(frame)?;
+    }
+
+    w.flush()
+}
+
+#[cfg(feature = "tokio")]
+pub async fn send_packet_async(p: &[u8],
+                               r: &SocketAddr)
+                               -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let socket = tokio::net::UdpSocket::bind(local_addr(r, &SendOptions::default())).await?;
+
+    if r.is_ipv4() {
+        socket.set_broadcast(true)?;
+    }
+
+    let sent = socket.send_to(p, r).await?;
+
+    Ok(check_whole(p, sent)?)
+}
+
+#[cfg(feature = "raw")]
+pub fn send_raw_packet(interface: &str, dst: &Mac, p: &[u8]) -> Result<usize, Box<dyn Error>> {
+    use crate::build_ethernet_frame;
+    use pnet_datalink::Channel;
+
+    let iface = pnet_datalink::interfaces()
+        .into_iter()
+        .find(|i| i.name == interface)
+        .ok_or_else(|| format!("no interface named {}", interface))?;
+
+    let src = iface.mac.map(|m| Mac(m.octets())).unwrap_or_default();
+
+    let mut tx = match pnet_datalink::channel(&iface, Default::default()) {
+        Ok(Channel::Ethernet(tx, _)) => tx,
+        Ok(_) => return Err(format!("unsupported channel type on {}", interface).into()),
+        Err(e) => {
+            return Err(format!("could not open raw socket on {} (requires root or CAP_NET_RAW): \
+                                {}",
+                               interface,
+                               e)
+                .into())
+        }
+    };
+
+    let frame = build_ethernet_frame(dst, &src, p);
+
+    match tx.send_to(&frame, None) {
+        Some(Ok(())) => Ok(frame.len()),
+        Some(Err(e)) => Err(Box::new(e)),
+        None => Err(format!("could not send frame on {}", interface).into()),
+    }
+}
+
+pub fn send_packet_retry(p: &[u8],
+                         r: &SocketAddr,
+                         max_attempts: u32,
+                         base_delay: Duration)
+                         -> Result<usize, Box<dyn Error>> {
+    send_packet_retry_with(p, r, &SendOptions::default(), max_attempts, base_delay)
+}
+
+pub fn send_packet_retry_with(p: &[u8],
+                              r: &SocketAddr,
+                              opts: &SendOptions,
+                              max_attempts: u32,
+                              base_delay: Duration)
+                              -> Result<usize, Box<dyn Error>> {
+    retry(r, max_attempts, base_delay, || send_packet_with(p, r, opts))
+}
+
+fn retry<T, E: fmt::Display>(r: &SocketAddr,
+                             max_attempts: u32,
+                             base_delay: Duration,
+                             mut send: impl FnMut() -> Result<T, E>)
+                             -> Result<T, E> {
+    let mut delay = base_delay;
+    let mut attempt = 1;
+
+    loop {
+        match send() {
+            Ok(n) => return Ok(n),
+            Err(e) if attempt >= max_attempts => return Err(e),
+            Err(e) => {
+                warn!("attempt {} of {} to {} failed: {}", attempt, max_attempts, r, e);
+                thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+pub fn send_packet_repeated(p: &[u8],
+                            r: &SocketAddr,
+                            opts: &SendOptions,
+                            count: u32,
+                            interval: Duration)
+                            -> Result<usize, Box<dyn Error>> {
+    send_packet_repeated_retry(p, r, opts, count, interval, 1, Duration::from_millis(0))
+}
+
+// Each of the count packets gets up to max_attempts tries before the run gives up.
+pub fn send_packet_repeated_retry(p: &[u8],
+                                  r: &SocketAddr,
+                                  opts: &SendOptions,
+                                  count: u32,
+                                  interval: Duration,
+                                  max_attempts: u32,
+                                  base_delay: Duration)
+                                  -> Result<usize, Box<dyn Error>> {
+    let mut total = 0;
+
+    for i in 0..count {
+        if i > 0 {
+            thread::sleep(interval);
+        }
+        total += send_packet_retry_with(p, r, opts, max_attempts, base_delay)?;
+    }
+
+    Ok(total)
+}
+
+pub fn wait_for_host(addr: &SocketAddr,
+                     timeout: Duration,
+                     interval: Duration)
+                     -> Result<Duration, Box<dyn Error>> {
+    let start = Instant::now();
+
+    loop {
+        let remaining = match timeout.checked_sub(start.elapsed()) {
+            Some(r) if r > Duration::from_millis(0) => r,
+            _ => return Err(Box::new(WolError::WaitTimedOut)),
+        };
+        let attempt = Instant::now();
+
+        match TcpStream::connect_timeout(addr, interval.min(remaining)) {
+            Ok(_) => return Ok(start.elapsed()),
+            Err(e) => debug!("{} is not up yet: {}", addr, e),
+        }
+
+        if let Some(rest) = interval.checked_sub(attempt.elapsed()) {
+            thread::sleep(rest.min(timeout.saturating_sub(start.elapsed())));
+        }
+    }
+}
+
+pub fn self_test() -> Result<Mac, Box<dyn Error>> {
+    let mac = Mac([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    let receiver = UdpSocket::bind((Ipv4Addr::new(127, 0, 0, 1), 0))?;
+    receiver.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+
+    send_packet(&build_packet(&mac)?, &receiver.local_addr()?)?;
+
+    let mut buf = [0; 256];
+    let (len, _) = receiver.recv_from(&mut buf)?;
+
+    if !is_magic_packet(&buf[..len]) {
+        return Err(Box::new(WolError::SelfTestFailed));
+    }
+
+    match parse_packet(&buf[..len]) {
+        Some(received) if received == mac => Ok(received),
+        _ => Err(Box::new(WolError::SelfTestFailed)),
+    }
+}
+
+pub fn wake<A: Into<IpAddr>>(mac: &Mac, broadcast: A, port: u16) -> Result<usize, Box<dyn Error>> {
+    let packet = build_packet(mac)?;
+
+    send_packet(&packet, &SocketAddr::new(broadcast.into(), port))
+}
+
+#[derive(Debug, Clone)]
+pub struct WakeRequest {
+    mac: Mac,
+    broadcast: IpAddr,
+    port: u16,
+    count: u32,
+    interval: Duration,
+    password: Option<Password>,
+    source: Option<IpAddr>,
+}
+
+impl WakeRequest {
+    pub fn new(mac: Mac) -> WakeRequest {
+        WakeRequest {
+            mac,
+            broadcast: IpAddr::V4(Ipv4Addr::BROADCAST),
+            port: 9,
+            count: 1,
+            interval: Duration::from_millis(100),
+            password: None,
+            source: None,
+        }
+    }
+
+    pub fn broadcast<A: Into<IpAddr>>(mut self, addr: A) -> WakeRequest {
+        self.broadcast = addr.into();
+        self
+    }
+
+    pub fn port(mut self, port: u16) -> WakeRequest {
+        self.port = port;
+        self
+    }
+
+    pub fn count(mut self, count: u32) -> WakeRequest {
+        self.count = count;
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> WakeRequest {
+        self.interval = interval;
+        self
+    }
+
+    pub fn password(mut self, password: Password) -> WakeRequest {
+        self.password = Some(password);
+        self
+    }
+
+    pub fn source<A: Into<IpAddr>>(mut self, addr: A) -> WakeRequest {
+        self.source = Some(addr.into());
+        self
+    }
+
+    pub fn build(self) -> Result<Wake, Box<dyn Error>> {
+        if self.port == 0 {
+            return Err("port must be greater than zero".into());
+        }
+
+        if self.count == 0 {
+            return Err("count must be greater than zero".into());
+        }
+
+        if let Some(source) = self.source {
+            if source.is_ipv4() != self.broadcast.is_ipv4() {
+                return Err(format!("source {} and broadcast {} are different address families",
+                                   source,
+                                   self.broadcast)
+                    .into());
+            }
+        }
+
+        let packet = match self.password {
+            Some(ref password) => build_packet_with_password(&self.mac, password)?,
+            None => build_packet(&self.mac)?,
+        };
+
+        Ok(Wake {
+            packet,
+            target: SocketAddr::new(self.broadcast, self.port),
+            count: self.count,
+            interval: self.interval,
+            opts: SendOptions { source: self.source, ..SendOptions::default() },
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Wake {
+    packet: Vec<u8>,
+    target: SocketAddr,
+    count: u32,
+    interval: Duration,
+    opts: SendOptions,
+}
+
+impl Wake {
+    pub fn packet(&self) -> &[u8] {
+        &self.packet
+    }
+
+    pub fn target(&self) -> SocketAddr {
+        self.target
+    }
+
+    pub fn send(&self) -> Result<usize, Box<dyn Error>> {
+        send_packet_repeated(&self.packet, &self.target, &self.opts, self.count, self.interval)
+    }
+}
+
+pub type WakeResult = Result<usize, Box<dyn Error + Send + Sync>>;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct WakeReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub failures: Vec<(usize, String, String)>,
+}
+
+impl WakeReport {
+    pub fn fail(&mut self, line: usize, mac: &str, error: &str) {
+        self.failed += 1;
+        self.failures.push((line, mac.to_string(), error.to_string()));
+    }
+}
+
+pub fn wake_all(targets: &[(Mac, SocketAddr)]) -> Vec<WakeResult> {
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    wake_all_with(targets, threads)
+}
+
+pub fn wake_all_with(targets: &[(Mac, SocketAddr)], threads: usize) -> Vec<WakeResult> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<WakeResult>> = targets.iter().map(|_| None).collect();
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1).min(targets.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+
+                    loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        if i >= targets.len() {
+                            break;
+                        }
+
+                        let (ref mac, ref addr) = targets[i];
+                        done.push((i, wake_target(mac, addr)));
+                    }
+
+                    done
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            for (i, result) in worker.join().unwrap() {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    results.into_iter().map(|r| r.unwrap()).collect()
+}
+
+pub fn wake_from_file<P: AsRef<Path>>(path: P, addr: &SocketAddr) -> io::Result<WakeReport> {
+    let mut report = WakeReport::default();
+
+    for (line, content, parsed) in parse_mac_list(BufReader::new(File::open(path)?))? {
+        let result = match parsed {
+            Ok(mac) => wake_target(&mac, addr).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(_) => report.succeeded += 1,
+            Err(e) => report.fail(line, &content, &e),
+        }
+    }
+
+    Ok(report)
+}
+
+fn wake_target(mac: &Mac, addr: &SocketAddr) -> WakeResult {
+    let packet = build_packet(mac)?;
+    let socket = bind_socket(addr, &SendOptions::default())?;
+
+    Ok(send_whole(&socket, &packet, addr, false)?)
+}
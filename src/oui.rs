@@ -0,0 +1,67 @@
+// A trimmed subset of the IEEE OUI registry, sorted by prefix.
+static VENDORS: &[([u8; 3], &str)] = &[([0x00, 0x00, 0x0c], "Cisco"),
+                                       ([0x00, 0x02, 0xb3], "Intel"),
+                                       ([0x00, 0x03, 0x93], "Apple"),
+                                       ([0x00, 0x04, 0x4b], "NVIDIA"),
+                                       ([0x00, 0x05, 0x5d], "D-Link"),
+                                       ([0x00, 0x05, 0x69], "VMware"),
+                                       ([0x00, 0x08, 0x9b], "QNAP"),
+                                       ([0x00, 0x09, 0x5b], "Netgear"),
+                                       ([0x00, 0x0a, 0x95], "Apple"),
+                                       ([0x00, 0x0c, 0x29], "VMware"),
+                                       ([0x00, 0x0c, 0x6e], "ASUSTek"),
+                                       ([0x00, 0x0d, 0x3a], "Microsoft"),
+                                       ([0x00, 0x11, 0x32], "Synology"),
+                                       ([0x00, 0x12, 0xfb], "Samsung"),
+                                       ([0x00, 0x14, 0x22], "Dell"),
+                                       ([0x00, 0x14, 0x6c], "Netgear"),
+                                       ([0x00, 0x15, 0x5d], "Microsoft"),
+                                       ([0x00, 0x15, 0x99], "Samsung"),
+                                       ([0x00, 0x16, 0x3e], "Xensource"),
+                                       ([0x00, 0x17, 0xf2], "Apple"),
+                                       ([0x00, 0x1a, 0x11], "Google"),
+                                       ([0x00, 0x1a, 0x92], "ASUSTek"),
+                                       ([0x00, 0x1b, 0x11], "D-Link"),
+                                       ([0x00, 0x1b, 0x21], "Intel"),
+                                       ([0x00, 0x1b, 0x63], "Apple"),
+                                       ([0x00, 0x1c, 0x14], "VMware"),
+                                       ([0x00, 0x1c, 0x42], "Parallels"),
+                                       ([0x00, 0x1e, 0xc2], "Apple"),
+                                       ([0x00, 0x22, 0x15], "ASUSTek"),
+                                       ([0x00, 0x25, 0x00], "Apple"),
+                                       ([0x00, 0x25, 0x90], "Super Micro"),
+                                       ([0x00, 0x30, 0x48], "Super Micro"),
+                                       ([0x00, 0x50, 0x56], "VMware"),
+                                       ([0x00, 0x50, 0xf2], "Microsoft"),
+                                       ([0x00, 0x90, 0x27], "Intel"),
+                                       ([0x00, 0x90, 0xa9], "Western Digital"),
+                                       ([0x00, 0xa0, 0xc9], "Intel"),
+                                       ([0x00, 0xe0, 0x4c], "Realtek"),
+                                       ([0x08, 0x00, 0x27], "PCS Systemtechnik (VirtualBox)"),
+                                       ([0x3c, 0x5a, 0xb4], "Google"),
+                                       ([0xb8, 0x27, 0xeb], "Raspberry Pi Foundation"),
+                                       ([0xdc, 0xa6, 0x32], "Raspberry Pi Trading"),
+                                       ([0xe4, 0x5f, 0x01], "Raspberry Pi Trading")];
+
+pub fn lookup(oui: [u8; 3]) -> Option<&'static str> {
+    VENDORS.binary_search_by_key(&oui, |&(prefix, _)| prefix)
+        .ok()
+        .map(|i| VENDORS[i].1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lookup, VENDORS};
+
+    #[test]
+    fn vendor_table_is_sorted() {
+        assert!(VENDORS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn can_lookup_vendor() {
+        assert_eq!(lookup([0x00, 0x1b, 0x63]), Some("Apple"));
+        assert_eq!(lookup([0x00, 0x50, 0x56]), Some("VMware"));
+        assert_eq!(lookup([0x02, 0x00, 0x00]), None);
+    }
+}
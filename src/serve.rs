@@ -0,0 +1,236 @@
+use std::error::Error;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Deserialize;
+use tiny_http::{Header, Method, Response, Server};
+
+#[cfg(test)]
+mod test {
+    use super::{handle, read_body, reject, Metrics, MAX_BODY};
+    use std::io::Cursor;
+    use std::net::UdpSocket;
+    use tiny_http::Method;
+
+    #[test]
+    fn can_handle_wake_request() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let body = format!("{{\"mac\":\"00:1a:2b:3c:4d:5e\",\"broadcast\":\"127.0.0.1\",\
+                            \"port\":{}}}",
+                           port);
+        let (status, _) = handle(&body, &Metrics::default());
+        assert_eq!(status, 200);
+
+        let mut buf = [0; 256];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(wol::parse_packet(&buf[..len]),
+                   Some("00:1a:2b:3c:4d:5e".parse().unwrap()));
+    }
+
+    #[test]
+    fn return_bad_request_for_invalid_body() {
+        let metrics = Metrics::default();
+        assert_eq!(handle("{\"mac\":\"zz\",\"broadcast\":\"127.0.0.1\"}", &metrics).0, 400);
+        assert_eq!(handle("{\"broadcast\":\"127.0.0.1\"}", &metrics).0, 400);
+        assert_eq!(handle("not json", &metrics).0, 400);
+        assert!(metrics.render().contains("wol_requests_total{status=\"400\"} 3\n"));
+    }
+
+    #[test]
+    fn can_route_requests() {
+        assert_eq!(reject(&Method::Post, "/"), None);
+        assert_eq!(reject(&Method::Get, "/metrics"), None);
+        assert_eq!(reject(&Method::Get, "/").map(|r| r.0), Some(405));
+        assert_eq!(reject(&Method::Post, "/metrics").map(|r| r.0), Some(405));
+        assert_eq!(reject(&Method::Post, "/shutdown").map(|r| r.0), Some(404));
+    }
+
+    #[test]
+    fn return_payload_too_large_for_long_body() {
+        let body = "x".repeat(MAX_BODY as usize);
+        assert_eq!(read_body(Cursor::new(body.as_bytes())), Ok(body.clone()));
+
+        let body = body + "x";
+        assert_eq!(read_body(Cursor::new(body.as_bytes())).unwrap_err().0, 413);
+    }
+
+    #[test]
+    fn can_render_metrics() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let body = format!("{{\"mac\":\"00:1a:2b:3c:4d:5e\",\"broadcast\":\"127.0.0.1\",\
+                            \"port\":{}}}",
+                           port);
+
+        let metrics = Metrics::default();
+        assert_eq!(handle(&body, &metrics).0, 200);
+        assert_eq!(handle(&body, &metrics).0, 200);
+        metrics.record_request(405);
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE wol_packets_sent_total counter\n"));
+        assert!(text.contains("wol_packets_sent_total 2\n"));
+        assert!(text.contains("wol_send_errors_total 0\n"));
+        assert!(text.contains("wol_requests_total{status=\"200\"} 2\n"));
+        assert!(text.contains("wol_requests_total{status=\"405\"} 1\n"));
+    }
+}
+
+#[derive(Deserialize)]
+struct WakeRequest {
+    mac: wol::Mac,
+    broadcast: String,
+    port: Option<u16>,
+}
+
+const WAKE_PATH: &str = "/";
+const METRICS_PATH: &str = "/metrics";
+const MAX_BODY: u64 = 4096;
+
+const STATUSES: [u16; 6] = [200, 400, 404, 405, 413, 500];
+
+#[derive(Default)]
+pub struct Metrics {
+    packets_sent: AtomicUsize,
+    send_errors: AtomicUsize,
+    requests: [AtomicUsize; 6],
+}
+
+impl Metrics {
+    pub fn record_request(&self, status: u16) {
+        if let Some(i) = STATUSES.iter().position(|&s| s == status) {
+            self.requests[i].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = format!("# HELP wol_packets_sent_total Magic packets sent.\n\
+                               # TYPE wol_packets_sent_total counter\n\
+                               wol_packets_sent_total {}\n\
+                               # HELP wol_send_errors_total Magic packets that failed to send.\n\
+                               # TYPE wol_send_errors_total counter\n\
+                               wol_send_errors_total {}\n\
+                               # HELP wol_requests_total Wake requests by response status.\n\
+                               # TYPE wol_requests_total counter\n",
+                              self.packets_sent.load(Ordering::Relaxed),
+                              self.send_errors.load(Ordering::Relaxed));
+
+        for (status, n) in STATUSES.iter().zip(self.requests.iter()) {
+            out.push_str(&format!("wol_requests_total{{status=\"{}\"}} {}\n",
+                                  status,
+                                  n.load(Ordering::Relaxed)));
+        }
+
+        out
+    }
+}
+
+pub fn handle(body: &str, metrics: &Metrics) -> (u16, String) {
+    let (status, message) = wake(body, metrics);
+    metrics.record_request(status);
+    (status, message)
+}
+
+fn wake(body: &str, metrics: &Metrics) -> (u16, String) {
+    let request: WakeRequest = match serde_json::from_str(body) {
+        Ok(r) => r,
+        Err(e) => return (400, format!("could not parse request: {}", e)),
+    };
+
+    let raddr = match wol::resolve_destination(&request.broadcast, request.port.unwrap_or(9)) {
+        Ok(raddr) => raddr,
+        Err(e) => return (400, format!("could not resolve {}: {}", request.broadcast, e)),
+    };
+
+    let packet = match wol::build_packet(&request.mac) {
+        Ok(p) => p,
+        Err(e) => return (500, format!("could not build packet for {}: {}", request.mac, e)),
+    };
+
+    match wol::send_packet(&packet, &raddr) {
+        Ok(n) => {
+            metrics.packets_sent.fetch_add(1, Ordering::Relaxed);
+            (200, format!("sent {} bytes for {} to {}", n, request.mac, raddr))
+        }
+        Err(e) => {
+            metrics.send_errors.fetch_add(1, Ordering::Relaxed);
+            (500, format!("could not send request for {}: {}", request.mac, e))
+        }
+    }
+}
+
+fn reject(method: &Method, url: &str) -> Option<(u16, String)> {
+    match (method, url) {
+        (&Method::Post, WAKE_PATH) | (&Method::Get, METRICS_PATH) => None,
+        (_, WAKE_PATH) => Some((405, "only POST is supported".to_string())),
+        (_, METRICS_PATH) => Some((405, "only GET is supported".to_string())),
+        _ => Some((404, format!("no such path {}", url))),
+    }
+}
+
+fn read_body<R: Read>(reader: R) -> Result<String, (u16, String)> {
+    let mut body = String::new();
+
+    reader.take(MAX_BODY + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| (400, format!("could not read request body: {}", e)))?;
+
+    if body.len() as u64 > MAX_BODY {
+        return Err((413, format!("request body is larger than {} bytes", MAX_BODY)));
+    }
+
+    Ok(body)
+}
+
+pub fn serve(addr: &str) -> Result<(), Box<dyn Error>> {
+    let server = Server::http(addr).map_err(|e| format!("could not listen on {}: {}", addr, e))?;
+
+    info!("listening for wake requests on {}", addr);
+
+    let metrics = Metrics::default();
+
+    for mut request in server.incoming_requests() {
+        let rejected = reject(request.method(), request.url());
+
+        if rejected.is_none() && request.url() == METRICS_PATH {
+            let content_type = Header::from_bytes(&b"Content-Type"[..],
+                                                  &b"text/plain; version=0.0.4"[..])
+                .unwrap();
+            let response = Response::from_string(metrics.render()).with_header(content_type);
+            if let Err(e) = request.respond(response) {
+                error!("could not send response: {}", e);
+            }
+            continue;
+        }
+
+        let (status, message) = match rejected {
+            Some((status, message)) => {
+                metrics.record_request(status);
+                (status, message)
+            }
+            None => {
+                match read_body(request.as_reader()) {
+                    Ok(body) => handle(&body, &metrics),
+                    Err((status, message)) => {
+                        metrics.record_request(status);
+                        (status, message)
+                    }
+                }
+            }
+        };
+
+        if status == 200 {
+            info!("{}", message);
+        } else {
+            error!("{} {}: {}", status, request.url(), message);
+        }
+
+        let response = Response::from_string(message).with_status_code(status);
+        if let Err(e) = request.respond(response) {
+            error!("could not send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::io::{self, Write};
+
+use crossterm::cursor::MoveUp;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::queue;
+use crossterm::terminal;
+
+#[cfg(test)]
+mod test {
+    use super::{select, Key};
+    use std::io;
+
+    fn names() -> Vec<String> {
+        vec!["nas".to_string(), "officepc".to_string(), "tv".to_string()]
+    }
+
+    #[test]
+    fn can_select_with_arrow_keys() {
+        let keys = vec![Key::Down, Key::Down, Key::Up, Key::Enter];
+        assert_eq!(select(&names(), keys, &mut io::sink()).unwrap(), Some(1));
+
+        let keys = vec![Key::Up, Key::Enter];
+        assert_eq!(select(&names(), keys, &mut io::sink()).unwrap(), Some(0));
+
+        let keys = vec![Key::Down, Key::Down, Key::Down, Key::Enter];
+        assert_eq!(select(&names(), keys, &mut io::sink()).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn can_select_by_number() {
+        let mut out = Vec::new();
+        assert_eq!(select(&names(), vec![Key::Digit(2)], &mut out).unwrap(), Some(1));
+        assert!(String::from_utf8(out).unwrap().contains("> 2. officepc\r\n"));
+
+        let keys = vec![Key::Digit(7), Key::Digit(3)];
+        assert_eq!(select(&names(), keys, &mut io::sink()).unwrap(), Some(2));
+    }
+
+    #[test]
+    fn can_cancel_selection() {
+        assert_eq!(select(&names(), vec![Key::Down, Key::Quit], &mut io::sink()).unwrap(), None);
+        assert_eq!(select(&names(), vec![Key::Down], &mut io::sink()).unwrap(), None);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Digit(usize),
+    Quit,
+}
+
+fn render<W: Write>(names: &[String], selected: usize, out: &mut W) -> io::Result<()> {
+    for (i, name) in names.iter().enumerate() {
+        let marker = if i == selected { '>' } else { ' ' };
+        write!(out, "{} {}. {}\r\n", marker, i + 1, name)?;
+    }
+
+    out.flush()
+}
+
+pub fn select<I, W>(names: &[String], keys: I, out: &mut W) -> io::Result<Option<usize>>
+    where I: IntoIterator<Item = Key>,
+          W: Write
+{
+    let mut selected = 0;
+    render(names, selected, out)?;
+
+    for key in keys {
+        match key {
+            Key::Up => selected = selected.saturating_sub(1),
+            Key::Down => selected = (selected + 1).min(names.len().saturating_sub(1)),
+            Key::Digit(n) if n >= 1 && n <= names.len() => selected = n - 1,
+            Key::Digit(_) => continue,
+            Key::Enter => return Ok(Some(selected)),
+            Key::Quit => return Ok(None),
+        }
+
+        queue!(out, MoveUp(names.len() as u16))?;
+        render(names, selected, out)?;
+
+        if let Key::Digit(_) = key {
+            return Ok(Some(selected));
+        }
+    }
+
+    Ok(None)
+}
+
+fn read_key() -> io::Result<Key> {
+    loop {
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => return Ok(Key::Up),
+                KeyCode::Down | KeyCode::Char('j') => return Ok(Key::Down),
+                KeyCode::Enter => return Ok(Key::Enter),
+                KeyCode::Char(c) if c.is_ascii_digit() => {
+                    return Ok(Key::Digit(c.to_digit(10).unwrap_or(0) as usize))
+                }
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(Key::Quit),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn pick(names: &[String]) -> Result<Option<usize>, Box<dyn Error>> {
+    terminal::enable_raw_mode().map_err(|e| format!("could not set up terminal: {}", e))?;
+
+    let mut failed = None;
+    let keys = std::iter::from_fn(|| match read_key() {
+        Ok(key) => Some(key),
+        Err(e) => {
+            failed = Some(e);
+            None
+        }
+    });
+    let result = select(names, keys, &mut io::stdout());
+
+    let _ = terminal::disable_raw_mode();
+
+    match failed {
+        Some(e) => Err(format!("could not read key: {}", e).into()),
+        None => Ok(result?),
+    }
+}
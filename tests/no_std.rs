@@ -0,0 +1,17 @@
+#![no_std]
+
+extern crate wol;
+
+use wol::{build_packet_array, is_magic_packet, parse_packet, Mac};
+
+#[test]
+fn can_build_packet_array_without_std() {
+    let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+    let packet = build_packet_array(&mac);
+
+    assert_eq!(packet.len(), 102);
+    assert_eq!(packet[..6], [0xff; 6]);
+    assert!(packet[6..].chunks(6).all(|c| c == [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+    assert!(is_magic_packet(&packet));
+    assert_eq!(parse_packet(&packet), Some(mac));
+}
@@ -0,0 +1,19 @@
+#![cfg(feature = "std")]
+
+extern crate wol;
+
+use std::net::{Ipv4Addr, UdpSocket};
+
+use wol::{build_packet, wake, Mac};
+
+#[test]
+fn can_wake_loopback() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = receiver.local_addr().unwrap().port();
+    let mac: Mac = "00:1a:2b:3c:4d:5e".parse().unwrap();
+    assert_eq!(wake(&mac, Ipv4Addr::new(127, 0, 0, 1), port).unwrap(), 102);
+
+    let mut buf = [0; 256];
+    let (len, _) = receiver.recv_from(&mut buf).unwrap();
+    assert_eq!(buf[..len].to_vec(), build_packet(&mac).unwrap());
+}